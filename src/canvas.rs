@@ -109,6 +109,36 @@ impl<'a> Canvas<'a> {
         }
     }
 
+    /// Draws the image blended halfway toward the `tint` color, with the
+    /// opacity scaled by the tint's alpha.
+    pub fn draw_image_tinted(
+        &mut self,
+        image: &ahi::Image,
+        palette: &ahi::Palette,
+        left: i32,
+        top: i32,
+        scale: u32,
+        tint: (u8, u8, u8, u8),
+    ) {
+        let (tr, tg, tb, ta) = tint;
+        let blend = |c: u8, t: u8| ((c as u32 + t as u32) / 2) as u8;
+        for row in 0..image.height() {
+            for col in 0..image.width() {
+                let (r, g, b, a) = palette[image[(col, row)]];
+                let alpha = ((a as u32) * (ta as u32) / 255) as u8;
+                self.fill_rect(
+                    (blend(r, tr), blend(g, tg), blend(b, tb), alpha),
+                    Rect::new(
+                        left + (scale * col) as i32,
+                        top + (scale * row) as i32,
+                        scale,
+                        scale,
+                    ),
+                );
+            }
+        }
+    }
+
     pub fn draw_pixel(&mut self, color: (u8, u8, u8, u8), point: Point) {
         self.fill_rect(color, Rect::new(point.x(), point.y(), 1, 1));
     }
@@ -208,6 +238,10 @@ pub struct Font<'a> {
 }
 
 impl<'a> Font<'a> {
+    pub fn height(&self) -> u32 {
+        self.default_glyph.sprite.height()
+    }
+
     pub fn text_width(&self, text: &str) -> i32 {
         let mut width = 0;
         for chr in text.chars() {
@@ -271,12 +305,12 @@ pub enum ToolIcon {
     Eyedropper,
     Select,
     Line,
-    Checkerboard,
+    DitherFill,
     Oval,
     Rectangle,
     PaletteSwap,
     PaletteReplace,
-    Watercolor,
+    DitherBrush,
     Lasso,
     MirrorNone,
     MirrorHorz,
@@ -288,6 +322,16 @@ pub enum ToolIcon {
     ArrowRight,
     AddPalette,
     DeletePalette,
+    FilledOval,
+    FilledRectangle,
+    Polygon,
+    Curve,
+    Gradient,
+    Shade,
+    MirrorDiag,
+    MirrorAntiDiag,
+    MirrorKaleido,
+    Measure,
 }
 
 //===========================================================================//
@@ -0,0 +1,132 @@
+// +--------------------------------------------------------------------------+
+// | Copyright 2016 Matthew D. Steele <mdsteele@alum.mit.edu>                 |
+// |                                                                          |
+// | This file is part of Tuna.                                               |
+// |                                                                          |
+// | Tuna is free software: you can redistribute it and/or modify it under    |
+// | the terms of the GNU General Public License as published by the Free     |
+// | Software Foundation, either version 3 of the License, or (at your        |
+// | option) any later version.                                               |
+// |                                                                          |
+// | Tuna is distributed in the hope that it will be useful, but WITHOUT ANY  |
+// | WARRANTY; without even the implied warranty of MERCHANTABILITY or        |
+// | FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License    |
+// | for details.                                                             |
+// |                                                                          |
+// | You should have received a copy of the GNU General Public License along  |
+// | with Tuna.  If not, see <http://www.gnu.org/licenses/>.                  |
+// +--------------------------------------------------------------------------+
+
+use ahi::Color;
+use num_integer::mod_floor;
+
+//===========================================================================//
+
+const MATRIX_SIZES: &[u32] = &[2, 4, 8];
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct Dither {
+    size: u32,
+    level: u32,
+}
+
+impl Dither {
+    pub fn new(size: u32, level: u32) -> Option<Dither> {
+        if MATRIX_SIZES.contains(&size) && level <= size * size {
+            Some(Dither { size, level })
+        } else {
+            None
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<Dither> {
+        let text = text.trim();
+        if let Some(percent) = text.strip_suffix('%') {
+            let percent = percent.trim().parse::<f64>().ok()?;
+            if !(0.0..=100.0).contains(&percent) {
+                return None;
+            }
+            // Use the smallest matrix that can represent the level exactly,
+            // falling back to the closest level in the largest matrix.
+            for &size in MATRIX_SIZES {
+                let cells = (size * size) as f64;
+                let level = percent * cells / 100.0;
+                if level == level.round() {
+                    return Dither::new(size, level as u32);
+                }
+            }
+            Dither::new(8, (percent * 64.0 / 100.0).round() as u32)
+        } else {
+            let pieces: Vec<&str> = text.split('/').collect();
+            if pieces.len() != 2 {
+                return None;
+            }
+            let level = pieces[0].trim().parse::<u32>().ok()?;
+            let cells = pieces[1].trim().parse::<u32>().ok()?;
+            let size = MATRIX_SIZES
+                .iter()
+                .cloned()
+                .find(|&size| size * size == cells)?;
+            Dither::new(size, level)
+        }
+    }
+
+    pub fn size(self) -> u32 {
+        self.size
+    }
+
+    pub fn level(self) -> u32 {
+        self.level
+    }
+
+    pub fn is_primary(self, (x, y): (u32, u32)) -> bool {
+        bayer_threshold(self.size, x % self.size, y % self.size) < self.level
+    }
+
+    pub fn color_at(
+        self,
+        position: (u32, u32),
+        primary: Color,
+        secondary: Option<Color>,
+    ) -> Option<Color> {
+        if self.is_primary(position) {
+            Some(primary)
+        } else {
+            secondary
+        }
+    }
+}
+
+impl Default for Dither {
+    fn default() -> Dither {
+        Dither { size: 2, level: 2 }
+    }
+}
+
+//===========================================================================//
+
+pub fn ordered_threshold((x, y): (i32, i32)) -> f64 {
+    let size = MATRIX_SIZES[MATRIX_SIZES.len() - 1];
+    let (col, row) = (mod_floor(x, size as i32), mod_floor(y, size as i32));
+    let threshold = bayer_threshold(size, col as u32, row as u32);
+    (threshold as f64 + 0.5) / ((size * size) as f64)
+}
+
+fn bayer_threshold(size: u32, x: u32, y: u32) -> u32 {
+    // Each doubling of the matrix size splits every cell of the smaller
+    // matrix into a 2x2 block ordered 0, 2 / 3, 1.
+    if size <= 1 {
+        0
+    } else {
+        let half = size / 2;
+        let quadrant = match (x >= half, y >= half) {
+            (false, false) => 0,
+            (true, true) => 1,
+            (true, false) => 2,
+            (false, true) => 3,
+        };
+        4 * bayer_threshold(half, x % half, y % half) + quadrant
+    }
+}
+
+//===========================================================================//
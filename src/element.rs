@@ -168,7 +168,7 @@ where
 
     fn on_event(&mut self, event: &Event, state: &mut S) -> Action<A> {
         match event {
-            &Event::MouseDown(pt) => {
+            &Event::MouseDown(pt, _) => {
                 if !self.subrect.contains_point(pt) {
                     return Action::ignore();
                 }
@@ -20,7 +20,7 @@
 use sdl2;
 
 pub use sdl2::keyboard::Keycode;
-use sdl2::keyboard::Mod;
+use sdl2::keyboard::{KeyboardUtil, Mod};
 use sdl2::mouse::MouseButton;
 use sdl2::rect::Point;
 use std::ops::{BitOr, BitOrAssign};
@@ -31,20 +31,24 @@ use std::ops::{BitOr, BitOrAssign};
 pub enum Event {
     Quit,
     ClockTick,
-    MouseDrag(Point),
-    MouseDown(Point),
+    MouseDrag(Point, KeyMod),
+    MouseDown(Point, KeyMod),
     MouseUp,
     KeyDown(Keycode, KeyMod),
     TextInput(String),
 }
 
 impl Event {
-    pub fn from_sdl2(event: &sdl2::event::Event) -> Option<Event> {
+    pub fn from_sdl2(
+        event: &sdl2::event::Event,
+        keyboard: &KeyboardUtil,
+    ) -> Option<Event> {
         match event {
             &sdl2::event::Event::Quit { .. } => Some(Event::Quit),
             &sdl2::event::Event::MouseMotion { x, y, mousestate, .. } => {
                 if mousestate.left() {
-                    Some(Event::MouseDrag(Point::new(x, y)))
+                    let kmod = KeyMod::from_sdl2(keyboard.mod_state());
+                    Some(Event::MouseDrag(Point::new(x, y), kmod))
                 } else {
                     None
                 }
@@ -54,7 +58,10 @@ impl Event {
                 x,
                 y,
                 ..
-            } => Some(Event::MouseDown(Point::new(x, y))),
+            } => {
+                let kmod = KeyMod::from_sdl2(keyboard.mod_state());
+                Some(Event::MouseDown(Point::new(x, y), kmod))
+            }
             &sdl2::event::Event::MouseButtonUp {
                 mouse_btn: MouseButton::Left,
                 ..
@@ -73,8 +80,12 @@ impl Event {
 
     pub fn translate(&self, dx: i32, dy: i32) -> Event {
         match self {
-            &Event::MouseDrag(pt) => Event::MouseDrag(pt.offset(dx, dy)),
-            &Event::MouseDown(pt) => Event::MouseDown(pt.offset(dx, dy)),
+            &Event::MouseDrag(pt, kmod) => {
+                Event::MouseDrag(pt.offset(dx, dy), kmod)
+            }
+            &Event::MouseDown(pt, kmod) => {
+                Event::MouseDown(pt.offset(dx, dy), kmod)
+            }
             _ => self.clone(),
         }
     }
@@ -112,6 +123,10 @@ impl KeyMod {
 
         result
     }
+
+    pub fn contains(self, other: KeyMod) -> bool {
+        (self.bits & other.bits) == other.bits
+    }
 }
 
 impl BitOr for KeyMod {
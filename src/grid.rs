@@ -0,0 +1,190 @@
+// +--------------------------------------------------------------------------+
+// | Copyright 2016 Matthew D. Steele <mdsteele@alum.mit.edu>                 |
+// |                                                                          |
+// | This file is part of Tuna.                                               |
+// |                                                                          |
+// | Tuna is free software: you can redistribute it and/or modify it under    |
+// | the terms of the GNU General Public License as published by the Free     |
+// | Software Foundation, either version 3 of the License, or (at your        |
+// | option) any later version.                                               |
+// |                                                                          |
+// | Tuna is distributed in the hope that it will be useful, but WITHOUT ANY  |
+// | WARRANTY; without even the implied warranty of MERCHANTABILITY or        |
+// | FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License    |
+// | for details.                                                             |
+// |                                                                          |
+// | You should have received a copy of the GNU General Public License along  |
+// | with Tuna.  If not, see <http://www.gnu.org/licenses/>.                  |
+// +--------------------------------------------------------------------------+
+
+use num_integer::mod_floor;
+use std::cmp;
+
+//===========================================================================//
+
+const DEFAULT_COLOR: (u8, u8, u8, u8) = (192, 0, 255, 255);
+pub const GUIDE_COLOR: (u8, u8, u8, u8) = (0, 192, 255, 255);
+
+//===========================================================================//
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct Grid {
+    pub width: u32,
+    pub height: u32,
+    pub offset: (i32, i32),
+    pub subdivisions: u32,
+    pub color: (u8, u8, u8, u8),
+    pub pixel_grid: bool,
+}
+
+impl Default for Grid {
+    fn default() -> Grid {
+        Grid {
+            width: 0,
+            height: 0,
+            offset: (0, 0),
+            subdivisions: 1,
+            color: DEFAULT_COLOR,
+            pixel_grid: false,
+        }
+    }
+}
+
+impl Grid {
+    /// Parses a grid of the form "16x16 +4,0 /2 #c000ff px": the cell size
+    /// (0 for no lines), followed by an optional offset, number of minor
+    /// subdivisions per cell, line color (RGB or RGBA hex), and "px" to also
+    /// show a 1px pixel grid.
+    pub fn parse(text: &str) -> Option<Grid> {
+        let mut words = text.split_whitespace();
+        let size: Vec<&str> = words.next()?.split('x').collect();
+        if size.len() != 2 {
+            return None;
+        }
+        let mut grid = Grid {
+            width: size[0].parse().ok()?,
+            height: size[1].parse().ok()?,
+            ..Grid::default()
+        };
+        for word in words {
+            if let Some(offset) = word.strip_prefix('+') {
+                let pieces: Vec<&str> = offset.split(',').collect();
+                if pieces.len() != 2 {
+                    return None;
+                }
+                grid.offset =
+                    (pieces[0].parse().ok()?, pieces[1].parse().ok()?);
+            } else if let Some(subdivisions) = word.strip_prefix('/') {
+                grid.subdivisions = subdivisions.parse().ok()?;
+            } else if let Some(hex_text) = word.strip_prefix('#') {
+                let hex = u32::from_str_radix(hex_text, 16).ok()?;
+                grid.color = match hex_text.len() {
+                    6 => (
+                        (hex >> 16) as u8,
+                        (hex >> 8) as u8,
+                        hex as u8,
+                        u8::MAX,
+                    ),
+                    8 => (
+                        (hex >> 24) as u8,
+                        (hex >> 16) as u8,
+                        (hex >> 8) as u8,
+                        hex as u8,
+                    ),
+                    _ => return None,
+                };
+            } else if word == "px" {
+                grid.pixel_grid = true;
+            } else {
+                return None;
+            }
+        }
+        // A cell can't be divided more finely than into single pixels.
+        let cell = cmp::max(grid.width, grid.height);
+        if grid.subdivisions == 0 || (cell > 0 && grid.subdivisions > cell) {
+            return None;
+        }
+        Some(grid)
+    }
+
+    /// Formats the grid in the syntax accepted by `parse`, leaving out any
+    /// optional parts that have their default values.
+    pub fn format(&self) -> String {
+        let mut text = format!("{}x{}", self.width, self.height);
+        if self.offset != (0, 0) {
+            text.push_str(&format!(" +{},{}", self.offset.0, self.offset.1));
+        }
+        if self.subdivisions != 1 {
+            text.push_str(&format!(" /{}", self.subdivisions));
+        }
+        if self.color != DEFAULT_COLOR {
+            let (r, g, b, a) = self.color;
+            text.push_str(&format!(" #{:02x}{:02x}{:02x}", r, g, b));
+            if a != u8::MAX {
+                text.push_str(&format!("{:02x}", a));
+            }
+        }
+        if self.pixel_grid {
+            text.push_str(" px");
+        }
+        text
+    }
+
+    /// Returns the x-positions of the vertical grid lines within an image of
+    /// the given width, each paired with whether it is a major (cell) line.
+    pub fn columns(&self, width: u32) -> Vec<(u32, bool)> {
+        self.lines(self.width, self.offset.0, width)
+    }
+
+    /// Returns the y-positions of the horizontal grid lines within an image
+    /// of the given height, each paired with whether it is a major line.
+    pub fn rows(&self, height: u32) -> Vec<(u32, bool)> {
+        self.lines(self.height, self.offset.1, height)
+    }
+
+    fn lines(&self, cell: u32, offset: i32, extent: u32) -> Vec<(u32, bool)> {
+        let mut lines = Vec::new();
+        if cell == 0 {
+            return lines;
+        }
+        // Capping the subdivisions at the cell size keeps every step on a
+        // distinct pixel position.
+        let subdivisions = cmp::min(self.subdivisions, cell) as i64;
+        let cell = cell as i64;
+        let start = mod_floor(offset as i64, cell) - cell;
+        let mut index = 0;
+        loop {
+            let position = start + index * cell / subdivisions;
+            if position >= extent as i64 {
+                break;
+            }
+            if position > 0 {
+                lines.push((position as u32, index % subdivisions == 0));
+            }
+            index += 1;
+        }
+        lines
+    }
+}
+
+//===========================================================================//
+
+// A guide line at a pixel boundary: horizontal guides sit at a given y
+// position, and vertical guides at a given x position.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Guide {
+    Horz(u32),
+    Vert(u32),
+}
+
+impl Guide {
+    pub fn new(vertical: bool, position: u32) -> Guide {
+        if vertical {
+            Guide::Vert(position)
+        } else {
+            Guide::Horz(position)
+        }
+    }
+}
+
+//===========================================================================//
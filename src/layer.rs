@@ -0,0 +1,195 @@
+// +--------------------------------------------------------------------------+
+// | Copyright 2016 Matthew D. Steele <mdsteele@alum.mit.edu>                 |
+// |                                                                          |
+// | This file is part of Tuna.                                               |
+// |                                                                          |
+// | Tuna is free software: you can redistribute it and/or modify it under    |
+// | the terms of the GNU General Public License as published by the Free     |
+// | Software Foundation, either version 3 of the License, or (at your        |
+// | option) any later version.                                               |
+// |                                                                          |
+// | Tuna is distributed in the hope that it will be useful, but WITHOUT ANY  |
+// | WARRANTY; without even the implied warranty of MERCHANTABILITY or        |
+// | FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License    |
+// | for details.                                                             |
+// |                                                                          |
+// | You should have received a copy of the GNU General Public License along  |
+// | with Tuna.  If not, see <http://www.gnu.org/licenses/>.                  |
+// +--------------------------------------------------------------------------+
+
+use ahi::{Color, Image};
+use std::rc::Rc;
+
+//===========================================================================//
+
+#[derive(Clone)]
+pub struct Layer {
+    pub image: Rc<Image>,
+    pub visible: bool,
+    pub locked: bool,
+}
+
+impl Layer {
+    pub fn new(image: Rc<Image>) -> Layer {
+        Layer { image, visible: true, locked: false }
+    }
+}
+
+//===========================================================================//
+
+// The layers of a single image.  The pixels of the active layer live in the
+// document's image itself (so that the rest of the editor can treat it like
+// any other image), and the active layer's entry here is only kept up to date
+// when switching layers or saving.
+#[derive(Clone)]
+pub struct LayerStack {
+    layers: Vec<Layer>,
+    active: usize,
+}
+
+impl LayerStack {
+    pub fn new(layers: Vec<Layer>, active: usize) -> LayerStack {
+        debug_assert!(active < layers.len());
+        LayerStack { layers, active }
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    pub fn is_visible(&self, index: usize) -> bool {
+        self.layers[index].visible
+    }
+
+    pub fn is_locked(&self, index: usize) -> bool {
+        self.layers[index].locked
+    }
+
+    pub fn set_visible(&mut self, index: usize, visible: bool) {
+        self.layers[index].visible = visible;
+    }
+
+    pub fn set_locked(&mut self, index: usize, locked: bool) {
+        self.layers[index].locked = locked;
+    }
+
+    /// Returns all layers, using `live` as the active layer's image.
+    pub fn layers(&self, live: &Rc<Image>) -> Vec<Layer> {
+        let mut layers = self.layers.clone();
+        layers[self.active].image = live.clone();
+        layers
+    }
+
+    /// Returns the active layer's image, with the tag and metadata of the
+    /// flattened `saved` image.
+    pub fn live_image(&self, saved: &Image) -> Rc<Image> {
+        let mut live = self.layers[self.active].image.clone();
+        transfer_properties(saved, &mut live);
+        live
+    }
+
+    pub fn set_active(&mut self, index: usize, live: &mut Rc<Image>) {
+        if index == self.active {
+            return;
+        }
+        let mut next = self.layers[index].image.clone();
+        transfer_properties(live, &mut next);
+        self.layers[self.active].image = live.clone();
+        self.active = index;
+        *live = next;
+    }
+
+    pub fn insert(
+        &mut self,
+        index: usize,
+        layer: Layer,
+        live: &mut Rc<Image>,
+    ) {
+        self.layers[self.active].image = live.clone();
+        if index <= self.active {
+            self.active += 1;
+        }
+        self.layers.insert(index, layer);
+        self.set_active(index, live);
+    }
+
+    pub fn remove_active(&mut self, live: &mut Rc<Image>) {
+        debug_assert!(self.layers.len() > 1);
+        let removed = self.active;
+        let next = if removed > 0 { removed - 1 } else { 1 };
+        self.set_active(next, live);
+        self.layers.remove(removed);
+        if self.active > removed {
+            self.active -= 1;
+        }
+    }
+
+    pub fn move_active(&mut self, to: usize) {
+        let layer = self.layers.remove(self.active);
+        self.layers.insert(to, layer);
+        self.active = to;
+    }
+
+    /// Applies `func` to every layer other than the active one.
+    pub fn map_inactive(&mut self, func: &dyn Fn(&Image) -> Image) {
+        for (index, layer) in self.layers.iter_mut().enumerate() {
+            if index != self.active {
+                layer.image = Rc::new(func(&layer.image));
+            }
+        }
+    }
+
+    /// Flattens the visible layers (treating color 0 as transparent) into a
+    /// single image, with the tag and metadata of `live`.
+    pub fn composite(&self, live: &Image) -> Image {
+        let mut composite = live.clone();
+        composite.clear();
+        for (index, layer) in self.layers.iter().enumerate() {
+            if !layer.visible {
+                continue;
+            }
+            let image = if index == self.active { live } else { &layer.image };
+            let width = image.width().min(composite.width());
+            let height = image.height().min(composite.height());
+            for row in 0..height {
+                for col in 0..width {
+                    let color = image[(col, row)];
+                    if color != Color::C0 {
+                        composite[(col, row)] = color;
+                    }
+                }
+            }
+        }
+        composite
+    }
+
+    /// Returns true if every layer has the size of the flattened `saved`
+    /// image, and compositing the layers reproduces its pixels exactly.
+    pub fn matches(&self, saved: &Image) -> bool {
+        let width = saved.width();
+        let height = saved.height();
+        if self.layers.iter().any(|layer| {
+            layer.image.width() != width || layer.image.height() != height
+        }) {
+            return false;
+        }
+        let composite = self.composite(&self.layers[self.active].image);
+        (0..height).all(|row| {
+            (0..width).all(|col| composite[(col, row)] == saved[(col, row)])
+        })
+    }
+}
+
+//===========================================================================//
+
+fn transfer_properties(from: &Image, to: &mut Rc<Image>) {
+    let image = Rc::make_mut(to);
+    image.set_tag(from.tag().to_string());
+    image.set_metadata(from.metadata().clone());
+}
+
+//===========================================================================//
@@ -24,10 +24,16 @@
 // - Zoom/scroll
 
 mod canvas;
+mod dither;
 mod element;
 mod event;
+mod grid;
+mod layer;
 mod paint;
+mod reference;
+mod sidecar;
 mod state;
+mod transform;
 mod util;
 mod view;
 
@@ -89,6 +95,7 @@ fn main() {
     render_screen(&mut renderer, &resources, &state, &gui);
 
     let mut event_pump = sdl_context.event_pump().unwrap();
+    let keyboard = sdl_context.keyboard();
     let mut last_clock_tick = Instant::now();
     loop {
         let now = Instant::now();
@@ -106,7 +113,7 @@ fn main() {
                 last_clock_tick = now;
                 Event::ClockTick
             }
-            Some(sdl_event) => match Event::from_sdl2(&sdl_event) {
+            Some(sdl_event) => match Event::from_sdl2(&sdl_event, &keyboard) {
                 Some(event) => event,
                 None => continue,
             },
@@ -18,18 +18,54 @@
 // +--------------------------------------------------------------------------+
 
 use crate::canvas::{Canvas, Resources};
+use crate::dither;
 use crate::element::{Action, GuiElement};
-use crate::event::{Event, Keycode};
-use crate::state::{EditorState, Tool};
-use num_integer::mod_floor;
+use crate::event::{Event, KeyMod, Keycode, ALT, SHIFT};
+use crate::grid::{Grid, Guide, GUIDE_COLOR};
+use crate::state::{EditorState, Mirror, Tool};
+use crate::util;
+use ahi::{Color, Image};
+use num_integer::{div_floor, mod_floor};
 use sdl2::rect::{Point, Rect};
 use std::cmp;
+use std::collections::HashSet;
+use std::mem;
+use std::ops::Range;
 
 //===========================================================================//
 
-const GRID_COLOR: (u8, u8, u8, u8) = (192, 0, 255, 255);
+const PIXEL_GRID_COLOR: (u8, u8, u8, u8) = (128, 128, 128, 96);
+const PIXEL_GRID_MIN_SCALE: u32 = 6;
+const PREVIEW_COLOR: (u8, u8, u8, u8) = (192, 64, 192, 255);
+const HANDLE_COLOR: (u8, u8, u8, u8) = (0, 192, 192, 255);
+const AXIS_COLOR: (u8, u8, u8, u8) = (255, 128, 0, 255);
+const CHECKERBOARD_LIGHT: (u8, u8, u8, u8) = (204, 204, 204, 255);
+const CHECKERBOARD_DARK: (u8, u8, u8, u8) = (160, 160, 160, 255);
+const CHECKERBOARD_SIZE: u32 = 8;
+const INDEX_VIEW_COLORS: [(u8, u8, u8, u8); 16] = [
+    (255, 255, 255, 255),
+    (230, 25, 75, 255),
+    (60, 180, 75, 255),
+    (255, 225, 25, 255),
+    (67, 99, 216, 255),
+    (245, 130, 49, 255),
+    (145, 30, 180, 255),
+    (66, 212, 244, 255),
+    (240, 50, 230, 255),
+    (191, 239, 69, 255),
+    (250, 190, 212, 255),
+    (70, 153, 144, 255),
+    (220, 190, 255, 255),
+    (154, 99, 36, 255),
+    (255, 250, 200, 255),
+    (128, 128, 128, 255),
+];
+const DOUBLE_CLICK_TICKS: i32 = 4;
 
+#[derive(Clone, Copy)]
 enum Shape {
+    FilledOval,
+    FilledRect,
     Line,
     Oval,
     Rect,
@@ -38,18 +74,84 @@ enum Shape {
 impl Shape {
     fn from_tool(tool: Tool) -> Option<Shape> {
         match tool {
+            Tool::FilledOval => Some(Shape::FilledOval),
+            Tool::FilledRectangle => Some(Shape::FilledRect),
             Tool::Line => Some(Shape::Line),
             Tool::Oval => Some(Shape::Oval),
             Tool::Rectangle => Some(Shape::Rect),
             _ => None,
         }
     }
+
+    fn is_filled(self) -> bool {
+        match self {
+            Shape::FilledOval | Shape::FilledRect => true,
+            Shape::Line | Shape::Oval | Shape::Rect => false,
+        }
+    }
+}
+
+struct Gradient {
+    start: (i32, i32),
+    delta: (f64, f64),
+    radial: bool,
+    colors: Vec<Option<Color>>,
+}
+
+impl Gradient {
+    fn new(
+        state: &EditorState,
+        (start, end): ((i32, i32), (i32, i32)),
+        keymod: KeyMod,
+    ) -> Gradient {
+        Gradient {
+            start,
+            delta: ((end.0 - start.0) as f64, (end.1 - start.1) as f64),
+            radial: keymod.contains(ALT),
+            colors: gradient_colors(state, keymod.contains(SHIFT)),
+        }
+    }
+
+    fn param_at(&self, (x, y): (i32, i32)) -> f64 {
+        let (dx, dy) = self.delta;
+        let length_squared = dx * dx + dy * dy;
+        if length_squared == 0.0 {
+            return 0.0;
+        }
+        let px = (x - self.start.0) as f64;
+        let py = (y - self.start.1) as f64;
+        if self.radial {
+            ((px * px + py * py) / length_squared).sqrt()
+        } else {
+            (px * dx + py * dy) / length_squared
+        }
+    }
+
+    fn color_at(&self, position: (i32, i32)) -> Option<Color> {
+        if self.colors.len() < 2 {
+            return self.colors[0];
+        }
+        let last = self.colors.len() - 1;
+        let param = self.param_at(position).clamp(0.0, 1.0) * last as f64;
+        let index = cmp::min(param.floor() as usize, last - 1);
+        if param - (index as f64) > dither::ordered_threshold(position) {
+            self.colors[index + 1]
+        } else {
+            self.colors[index]
+        }
+    }
+}
+
+struct PencilPixel {
+    position: (u32, u32),
+    previous: Vec<((u32, u32), Color)>,
 }
 
 struct ImageCanvasDrag {
     from_selection: Point,
     from_pixel: Point,
     to_pixel: Point,
+    keymod: KeyMod,
 }
 
 pub struct ImageCanvas {
@@ -57,8 +159,15 @@ pub struct ImageCanvas {
     max_size: u32,
     drag_from_to: Option<ImageCanvasDrag>,
     lasso_points: Vec<(u32, u32)>,
+    pencil_trail: Vec<PencilPixel>,
+    shaded_pixels: HashSet<(u32, u32)>,
+    polygon_points: Vec<(i32, i32)>,
+    polygon_click_counter: i32,
+    curve_points: Vec<(i32, i32)>,
+    curve_drag: Option<usize>,
+    curve_cubic: bool,
+    measure_points: Option<((i32, i32), (i32, i32))>,
     selection_animation_counter: i32,
-    watercolor_parity: u32,
 }
 
 impl ImageCanvas {
@@ -68,8 +177,15 @@ impl ImageCanvas {
             max_size,
             drag_from_to: None,
             lasso_points: Vec::new(),
+            pencil_trail: Vec::new(),
+            shaded_pixels: HashSet::new(),
+            polygon_points: Vec::new(),
+            polygon_click_counter: 0,
+            curve_points: Vec::new(),
+            curve_drag: None,
+            curve_cubic: true,
+            measure_points: None,
             selection_animation_counter: 0,
-            watercolor_parity: 0,
         }
     }
 
@@ -102,6 +218,34 @@ impl ImageCanvas {
         }
     }
 
+    fn shape_points(
+        &self,
+        shape: Shape,
+        state: &EditorState,
+    ) -> Option<((i32, i32), (i32, i32))> {
+        if let Some(ref drag) = self.drag_from_to {
+            let (x0, y0) = self.mouse_to_vertex(drag.from_pixel, state);
+            let (x1, y1) = self.mouse_to_vertex(drag.to_pixel, state);
+            let keymod = drag.keymod;
+            let (mut dx, mut dy) = (x1 - x0, y1 - y0);
+            if keymod.contains(SHIFT) {
+                let (new_dx, new_dy) = match shape {
+                    Shape::Line => snap_line_delta(dx, dy),
+                    _ => square_delta(dx, dy),
+                };
+                dx = new_dx;
+                dy = new_dy;
+            }
+            if keymod.contains(ALT) {
+                Some(((x0 - dx, y0 - dy), (x0 + dx, y0 + dy)))
+            } else {
+                Some(((x0, y0), (x0 + dx, y0 + dy)))
+            }
+        } else {
+            None
+        }
+    }
+
     fn dragged_rect(&self, state: &EditorState) -> Option<Rect> {
         if let Some(((from_col, from_row), (to_col, to_row))) =
             self.dragged_points(state)
@@ -150,31 +294,129 @@ impl ImageCanvas {
         )
     }
 
-    fn try_pencil(&self, mouse: Point, state: &mut EditorState) -> bool {
+    fn mouse_to_vertex(
+        &self,
+        mouse: Point,
+        state: &EditorState,
+    ) -> (i32, i32) {
+        if state.seamless() {
+            // In seamless mode, shapes may run off the edge and wrap around.
+            let scale = self.scale(state) as i32;
+            let offset = mouse - self.top_left;
+            (div_floor(offset.x(), scale), div_floor(offset.y(), scale))
+        } else {
+            let (col, row) = self.clamp_mouse_to_row_col(mouse, state);
+            (col as i32, row as i32)
+        }
+    }
+
+    fn try_pencil(&mut self, mouse: Point, state: &mut EditorState) -> bool {
         if let Some(position) = self.mouse_to_row_col(mouse, state) {
-            state.persistent_mutation().color_pixel(position);
+            if state.pixel_perfect() && state.brush().is_none() {
+                return self.pixel_perfect_pencil(position, state);
+            }
+            state.persistent_mutation().stamp_brush(position);
             true
         } else {
             false
         }
     }
 
-    fn start_watercolor(&mut self, mouse: Point, state: &mut EditorState) {
-        if let Some(position) = self.mouse_to_row_col(mouse, state) {
-            self.watercolor_parity = (position.0 + position.1) % 2;
+    fn pixel_perfect_pencil(
+        &mut self,
+        position: (u32, u32),
+        state: &mut EditorState,
+    ) -> bool {
+        if let Some(last) = self.pencil_trail.last() {
+            if last.position == position {
+                return false;
+            }
+        }
+        let previous = state
+            .mirror_positions(position)
+            .into_iter()
+            .map(|pos| (pos, state.image()[pos]))
+            .collect();
+        self.pencil_trail.push(PencilPixel { position, previous });
+        let num_pixels = self.pencil_trail.len();
+        let mut corner = None;
+        if num_pixels >= 3 {
+            let before = self.pencil_trail[num_pixels - 3].position;
+            let middle = self.pencil_trail[num_pixels - 2].position;
+            if is_l_corner(before, middle, position) {
+                // Don't revert pixels that the neighbors' mirrors cover.
+                let mut keep = state.mirror_positions(before);
+                keep.extend(state.mirror_positions(position));
+                let middle_pixel = self.pencil_trail.remove(num_pixels - 2);
+                corner = Some((middle_pixel, keep));
+            }
         }
+        if self.pencil_trail.len() > 2 {
+            self.pencil_trail.remove(0);
+        }
+        let mut mutation = state.persistent_mutation();
+        mutation.color_pixel(position);
+        if let Some((pixel, keep)) = corner {
+            let image = mutation.image();
+            for (pos, color) in pixel.previous.into_iter() {
+                if !keep.contains(&pos) {
+                    image[pos] = color;
+                }
+            }
+        }
+        true
     }
 
-    fn try_watercolor(&self, mouse: Point, state: &mut EditorState) -> bool {
+    fn try_dither_brush(&self, mouse: Point, state: &mut EditorState) -> bool {
         if let Some(position) = self.mouse_to_row_col(mouse, state) {
-            if self.watercolor_parity == (position.0 + position.1) % 2 {
-                state.persistent_mutation().color_pixel(position);
+            let positions = state.mirror_positions(position);
+            let changes = dither_changes(state, positions);
+            if !changes.is_empty() {
+                let mut mutation = state.persistent_mutation();
+                let image = mutation.image();
+                for (position, color) in changes {
+                    image[position] = color;
+                }
                 return true;
             }
         }
         return false;
     }
 
+    fn try_shade(
+        &mut self,
+        mouse: Point,
+        state: &mut EditorState,
+        kmod: KeyMod,
+    ) -> bool {
+        if let Some(position) = self.mouse_to_row_col(mouse, state) {
+            let step = if kmod.contains(SHIFT) { -1 } else { 1 };
+            let mut changes = Vec::new();
+            for pos in state.mirror_positions(position) {
+                if !self.shaded_pixels.insert(pos) {
+                    continue;
+                }
+                let color = state.image()[pos];
+                if let Some(ramp) = state.ramp_containing(color) {
+                    let index = ramp.iter().position(|&c| c == color).unwrap();
+                    let new_index = index as i32 + step;
+                    if new_index >= 0 && (new_index as usize) < ramp.len() {
+                        changes.push((pos, ramp[new_index as usize]));
+                    }
+                }
+            }
+            if !changes.is_empty() {
+                let mut mutation = state.persistent_mutation();
+                let image = mutation.image();
+                for (pos, color) in changes {
+                    image[pos] = color;
+                }
+                return true;
+            }
+        }
+        false
+    }
+
     fn try_eyedrop(&self, mouse: Point, state: &mut EditorState) -> bool {
         if let Some(position) = self.mouse_to_row_col(mouse, state) {
             state.eyedrop_at(position);
@@ -199,18 +441,23 @@ impl ImageCanvas {
         shape: Shape,
         state: &mut EditorState,
     ) -> bool {
-        if let Some(((col1, row1), (col2, row2))) = self.dragged_points(state)
+        if let Some(((col1, row1), (col2, row2))) =
+            self.shape_points(shape, state)
         {
-            let (width, height) = state.image_size();
+            let outline = bresenham_shape(shape, col1, row1, col2, row2);
+            let interior = if shape.is_filled() {
+                image_points(state, shape_interior(&outline))
+            } else {
+                Vec::new()
+            };
+            let outline = image_points(state, outline);
+            let fill_color = state.secondary_color().unwrap_or(state.color());
             let mut mutation = state.mutation();
-            for (x, y) in bresenham_shape(shape, col1, row1, col2, row2) {
-                if x >= 0 && y >= 0 {
-                    let x = x as u32;
-                    let y = y as u32;
-                    if x < width && y < height {
-                        mutation.color_pixel((x, y));
-                    }
-                }
+            for position in interior {
+                mutation.color_pixel_with(position, fill_color);
+            }
+            for position in outline {
+                mutation.color_pixel(position);
             }
             self.drag_from_to = None;
             return true;
@@ -218,39 +465,153 @@ impl ImageCanvas {
         false
     }
 
-    fn try_checker_fill(&self, mouse: Point, state: &mut EditorState) -> bool {
+    fn add_polygon_vertex(
+        &mut self,
+        mouse: Point,
+        state: &mut EditorState,
+    ) -> bool {
+        let vertex = self.mouse_to_vertex(mouse, state);
+        let closes_loop =
+            self.polygon_points.len() >= 3 && self.polygon_points[0] == vertex;
+        let double_clicked = self.polygon_click_counter > 0
+            && self.polygon_points.last() == Some(&vertex);
+        if closes_loop || double_clicked {
+            return self.try_draw_polygon(state);
+        }
+        self.polygon_points.push(vertex);
+        self.polygon_click_counter = DOUBLE_CLICK_TICKS;
+        true
+    }
+
+    fn try_draw_polygon(&mut self, state: &mut EditorState) -> bool {
+        if self.polygon_points.is_empty() {
+            return false;
+        }
+        let mut vertices = self.polygon_points.split_off(0);
+        if vertices.len() >= 3 {
+            vertices.push(vertices[0]);
+        }
+        let points = image_points(state, polyline(&vertices));
+        let mut mutation = state.mutation();
+        for position in points {
+            mutation.color_pixel(position);
+        }
+        true
+    }
+
+    fn start_curve(&mut self, mouse: Point, state: &mut EditorState) -> bool {
+        let vertex = self.mouse_to_vertex(mouse, state);
+        if self.curve_points.is_empty() {
+            self.curve_points = vec![vertex, vertex];
+            self.curve_drag = Some(1);
+            return true;
+        }
+        let handle = self.curve_points.iter().position(|&(x, y)| {
+            (x - vertex.0).abs() <= 1 && (y - vertex.1).abs() <= 1
+        });
+        if handle.is_some() {
+            self.curve_drag = handle;
+            true
+        } else {
+            self.try_draw_curve(state)
+        }
+    }
+
+    fn finish_curve_drag(&mut self) -> bool {
+        if self.curve_drag.take().is_none() {
+            return false;
+        }
+        if self.curve_points.len() == 2 {
+            let (start, end) = (self.curve_points[0], self.curve_points[1]);
+            if start == end {
+                self.curve_points.clear();
+            } else if self.curve_cubic {
+                self.curve_points = vec![
+                    start,
+                    interpolate(start, end, 1.0 / 3.0),
+                    interpolate(start, end, 2.0 / 3.0),
+                    end,
+                ];
+            } else {
+                self.curve_points =
+                    vec![start, interpolate(start, end, 0.5), end];
+            }
+        }
+        true
+    }
+
+    fn toggle_curve_degree(&mut self) -> bool {
+        self.curve_cubic = !self.curve_cubic;
+        if self.curve_drag.is_some() {
+            return false;
+        }
+        match self.curve_points.len() {
+            3 => {
+                let (start, ctrl, end) = (
+                    self.curve_points[0],
+                    self.curve_points[1],
+                    self.curve_points[2],
+                );
+                self.curve_points = vec![
+                    start,
+                    interpolate(start, ctrl, 2.0 / 3.0),
+                    interpolate(end, ctrl, 2.0 / 3.0),
+                    end,
+                ];
+                true
+            }
+            4 => {
+                let (start, ctrl1, ctrl2, end) = (
+                    self.curve_points[0],
+                    self.curve_points[1],
+                    self.curve_points[2],
+                    self.curve_points[3],
+                );
+                let ctrl = interpolate(ctrl1, ctrl2, 0.5);
+                self.curve_points = vec![start, ctrl, end];
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn try_draw_curve(&mut self, state: &mut EditorState) -> bool {
+        if self.curve_points.len() < 3 {
+            return false;
+        }
+        let points = bezier_curve(&self.curve_points);
+        self.curve_points.clear();
+        self.curve_drag = None;
+        let points = image_points(state, points);
+        let mut mutation = state.mutation();
+        for position in points {
+            mutation.color_pixel(position);
+        }
+        true
+    }
+
+    fn cancel_path(&mut self) -> bool {
+        if self.polygon_points.is_empty() && self.curve_points.is_empty() {
+            return false;
+        }
+        self.polygon_points.clear();
+        self.curve_points.clear();
+        self.curve_drag = None;
+        true
+    }
+
+    fn try_flood_fill(&self, mouse: Point, state: &mut EditorState) -> bool {
         if let Some(start) = self.mouse_to_row_col(mouse, state) {
             let to_color = state.color();
-            let from_color = state.image()[start];
-            if from_color == to_color {
+            if state.image()[start] == to_color {
                 return false;
             }
+            let region =
+                contiguous_region(state.image(), start, state.seamless());
             let mut mutation = state.mutation();
             let image = mutation.image();
-            let width = image.width();
-            let height = image.height();
-            image[start] = to_color;
-            let mut stack: Vec<(u32, u32)> = vec![start];
-            while let Some((col, row)) = stack.pop() {
-                let mut next: Vec<(u32, u32)> = vec![];
-                if col > 0 && row > 0 {
-                    next.push((col - 1, row - 1));
-                }
-                if col < width - 1 && row < height - 1 {
-                    next.push((col + 1, row + 1));
-                }
-                if col < width - 1 && row > 0 {
-                    next.push((col + 1, row - 1));
-                }
-                if col > 0 && row < height - 1 {
-                    next.push((col - 1, row + 1));
-                }
-                for coords in next {
-                    if image[coords] == from_color {
-                        image[coords] = to_color;
-                        stack.push(coords);
-                    }
-                }
+            for position in region {
+                image[position] = to_color;
             }
             true
         } else {
@@ -258,39 +619,72 @@ impl ImageCanvas {
         }
     }
 
-    fn try_flood_fill(&self, mouse: Point, state: &mut EditorState) -> bool {
+    fn try_gradient_fill(&mut self, state: &mut EditorState) -> bool {
+        let points = match self.dragged_points(state) {
+            Some(points) => points,
+            None => return false,
+        };
+        let keymod = self.drag_from_to.take().unwrap().keymod;
+        let gradient = Gradient::new(state, points, keymod);
+        if let Some((selected, topleft)) = state.selection() {
+            let mut changes = Vec::new();
+            for row in 0..selected.height() {
+                for col in 0..selected.width() {
+                    let x = topleft.x() + col as i32;
+                    let y = topleft.y() + row as i32;
+                    if let Some(color) = gradient.color_at((x, y)) {
+                        if selected[(col, row)] != color {
+                            changes.push(((col, row), color));
+                        }
+                    }
+                }
+            }
+            if changes.is_empty() {
+                return false;
+            }
+            let mut mutation = state.mutation();
+            let image = mutation.selected_image().unwrap();
+            for (position, color) in changes {
+                image[position] = color;
+            }
+        } else {
+            let (start, _) = points;
+            let start = (start.0 as u32, start.1 as u32);
+            let image = state.image();
+            let changes: Vec<((u32, u32), Color)> =
+                contiguous_region(image, start, state.seamless())
+                    .into_iter()
+                    .filter_map(|(col, row)| {
+                        gradient
+                            .color_at((col as i32, row as i32))
+                            .filter(|&color| image[(col, row)] != color)
+                            .map(|color| ((col, row), color))
+                    })
+                    .collect();
+            if changes.is_empty() {
+                return false;
+            }
+            let mut mutation = state.mutation();
+            let image = mutation.image();
+            for (position, color) in changes {
+                image[position] = color;
+            }
+        }
+        true
+    }
+
+    fn try_dither_fill(&self, mouse: Point, state: &mut EditorState) -> bool {
         if let Some(start) = self.mouse_to_row_col(mouse, state) {
-            let to_color = state.color();
-            let from_color = state.image()[start];
-            if from_color == to_color {
+            let region =
+                contiguous_region(state.image(), start, state.seamless());
+            let changes = dither_changes(state, region);
+            if changes.is_empty() {
                 return false;
             }
             let mut mutation = state.mutation();
             let image = mutation.image();
-            let width = image.width();
-            let height = image.height();
-            image[start] = to_color;
-            let mut stack: Vec<(u32, u32)> = vec![start];
-            while let Some((col, row)) = stack.pop() {
-                let mut next: Vec<(u32, u32)> = vec![];
-                if col > 0 {
-                    next.push((col - 1, row));
-                }
-                if col < width - 1 {
-                    next.push((col + 1, row));
-                }
-                if row > 0 {
-                    next.push((col, row - 1));
-                }
-                if row < height - 1 {
-                    next.push((col, row + 1));
-                }
-                for coords in next {
-                    if image[coords] == from_color {
-                        image[coords] = to_color;
-                        stack.push(coords);
-                    }
-                }
+            for (position, color) in changes {
+                image[position] = color;
             }
             true
         } else {
@@ -332,6 +726,123 @@ impl ImageCanvas {
     }
 }
 
+impl ImageCanvas {
+    fn draw_reference(&self, state: &EditorState, canvas: &mut Canvas) {
+        let reference = match state.reference() {
+            Some(reference) => reference,
+            None => return,
+        };
+        let rect = self.rect(state);
+        let scale = self.scale(state) as f64;
+        let mut canvas = canvas.subcanvas(rect);
+        let left = (reference.offset.0 as f64) * scale;
+        let top = (reference.offset.1 as f64) * scale;
+        // The size, in screen pixels, of one reference pixel.
+        let size = reference.scale * scale;
+        if size >= 1.0 {
+            let cols =
+                visible_range(left, size, rect.width(), reference.width());
+            let rows =
+                visible_range(top, size, rect.height(), reference.height());
+            for row in rows {
+                let y0 = (top + (row as f64) * size).floor() as i32;
+                let y1 = (top + ((row + 1) as f64) * size).floor() as i32;
+                for col in cols.clone() {
+                    let x0 = (left + (col as f64) * size).floor() as i32;
+                    let x1 = (left + ((col + 1) as f64) * size).floor() as i32;
+                    canvas.fill_rect(
+                        reference.pixel(col, row),
+                        Rect::new(x0, y0, (x1 - x0) as u32, (y1 - y0) as u32),
+                    );
+                }
+            }
+        } else {
+            // Reference pixels are smaller than screen pixels, so sample the
+            // reference once per screen pixel instead.
+            for y in 0..rect.height() {
+                let row = ((y as f64 - top) / size).floor();
+                if row < 0.0 || row >= reference.height() as f64 {
+                    continue;
+                }
+                for x in 0..rect.width() {
+                    let col = ((x as f64 - left) / size).floor();
+                    if col < 0.0 || col >= reference.width() as f64 {
+                        continue;
+                    }
+                    canvas.draw_pixel(
+                        reference.pixel(col as u32, row as u32),
+                        Point::new(x as i32, y as i32),
+                    );
+                }
+            }
+        }
+    }
+
+    // Draws each pixel in a fixed false color for its color index (including
+    // color 0), labelled with its hex digit when zoomed in far enough.
+    fn draw_index_view(
+        &self,
+        state: &EditorState,
+        resources: &Resources,
+        canvas: &mut Canvas,
+    ) {
+        let image = state.composite_image();
+        let scale = self.scale(state);
+        let rect = self.rect(state);
+        let font = resources.font();
+        let show_digits = scale >= font.height() + 4;
+        for row in 0..image.height() {
+            for col in 0..image.width() {
+                let index = util::color_index(image[(col, row)]);
+                let x = rect.x() + (col * scale) as i32;
+                let y = rect.y() + (row * scale) as i32;
+                canvas.fill_rect(
+                    INDEX_VIEW_COLORS[index],
+                    Rect::new(x, y, scale, scale),
+                );
+                if show_digits {
+                    let digit = format!("{:X}", index);
+                    let width = font.text_width(&digit);
+                    canvas.draw_string(
+                        font,
+                        x + (scale as i32 - width) / 2,
+                        y + (scale - font.height()) as i32 / 2,
+                        &digit,
+                    );
+                }
+            }
+        }
+    }
+
+    fn draw_onion_skin(&self, state: &EditorState, canvas: &mut Canvas) {
+        let frames = state.onion_skin() as i32;
+        let current = state.image_index() as i32;
+        let num_images = state.num_images() as i32;
+        let scale = self.scale(state);
+        let mut canvas = canvas.subcanvas(self.rect(state));
+        // Draw the furthest frames first, so that nearer frames end up on top.
+        for distance in (1..=frames).rev() {
+            let alpha = (160 / (distance + 1)) as u8;
+            let neighbors = [
+                (current - distance, (255, 64, 64, alpha)),
+                (current + distance, (64, 160, 255, alpha)),
+            ];
+            for &(index, tint) in neighbors.iter() {
+                if index >= 0 && index < num_images {
+                    canvas.draw_image_tinted(
+                        &state.composite_image_at(index as usize),
+                        state.palette(),
+                        0,
+                        0,
+                        scale,
+                        tint,
+                    );
+                }
+            }
+        }
+    }
+}
+
 impl GuiElement<EditorState, ()> for ImageCanvas {
     fn draw(
         &self,
@@ -341,14 +852,28 @@ impl GuiElement<EditorState, ()> for ImageCanvas {
     ) {
         let scale = self.scale(state);
         let canvas_rect = self.rect(state);
-        canvas.draw_rect((255, 255, 255, 255), expand(canvas_rect, 2));
-        canvas.draw_image(
-            state.image(),
-            state.palette(),
-            canvas_rect.x(),
-            canvas_rect.y(),
-            scale,
-        );
+        let border_color = if state.seamless() {
+            (0, 255, 255, 255)
+        } else {
+            (255, 255, 255, 255)
+        };
+        canvas.draw_rect(border_color, expand(canvas_rect, 2));
+        if state.checkerboard() {
+            draw_checkerboard(canvas, canvas_rect);
+        }
+        self.draw_reference(state, canvas);
+        self.draw_onion_skin(state, canvas);
+        if state.index_view() {
+            self.draw_index_view(state, resources, canvas);
+        } else {
+            canvas.draw_image(
+                &state.composite_image(),
+                state.palette(),
+                canvas_rect.x(),
+                canvas_rect.y(),
+                scale,
+            );
+        }
         if let Some((baseline, left_edge, right_edge)) = state.image_metrics()
         {
             canvas.draw_rect(
@@ -394,23 +919,34 @@ impl GuiElement<EditorState, ()> for ImageCanvas {
                 canvas_rect.x() + (topleft.x() * (scale as i32)).max(0),
                 canvas_rect.y() + (topleft.y() * (scale as i32)).max(0),
             );
-            canvas.fill_rect(
-                (255, 255, 255, 255),
-                Rect::new(
-                    topleft.x() + 1,
-                    topleft.y() - 11,
-                    (resources.font().text_width(&label) + 1) as u32,
-                    10,
-                ),
-            );
-            canvas.draw_string(
-                resources.font(),
-                topleft.x() + 2,
-                topleft.y() - 11,
-                &label,
-            );
+            draw_label(canvas, resources, topleft, &label);
+        }
+        if state.tool() == Tool::Measure {
+            if let Some((start, end)) = self.measure_points {
+                let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+                let distance = ((dx * dx + dy * dy) as f64).sqrt();
+                let label = format!("{},{} ({:.2})", dx, dy, distance);
+                let topleft = Point::new(
+                    canvas_rect.x() + end.0 * (scale as i32),
+                    canvas_rect.y() + end.1 * (scale as i32),
+                );
+                draw_label(canvas, resources, topleft, &label);
+            }
         }
         let mut canvas = canvas.subcanvas(canvas_rect);
+        if state.tool() == Tool::Gradient {
+            if let Some((start, end)) = self.dragged_points(state) {
+                draw_guide(&mut canvas, start, end, scale);
+                draw_handle(&mut canvas, start, scale);
+                draw_handle(&mut canvas, end, scale);
+            }
+        } else if state.tool() == Tool::Measure {
+            if let Some((start, end)) = self.measure_points {
+                draw_guide(&mut canvas, start, end, scale);
+                draw_handle(&mut canvas, start, scale);
+                draw_handle(&mut canvas, end, scale);
+            }
+        }
         if let Some((ref selected, topleft)) = state.selection() {
             let left = topleft.x() * (scale as i32);
             let top = topleft.y() * (scale as i32);
@@ -428,20 +964,15 @@ impl GuiElement<EditorState, ()> for ImageCanvas {
             );
         } else if let Some(shape) = Shape::from_tool(state.tool()) {
             if let Some(((col1, row1), (col2, row2))) =
-                self.dragged_points(state)
+                self.shape_points(shape, state)
             {
-                let (width, height) = state.image_size();
-                for (x, y) in bresenham_shape(shape, col1, row1, col2, row2) {
-                    if x >= 0
-                        && x <= (width as i32)
-                        && y >= 0
-                        && y < (height as i32)
-                    {
-                        for (col, row) in
-                            state.mirror_positions((x as u32, y as u32))
-                        {
-                            canvas.draw_rect(
-                                (192, 64, 192, 255),
+                let outline = bresenham_shape(shape, col1, row1, col2, row2);
+                if shape.is_filled() {
+                    let interior = shape_interior(&outline);
+                    for position in image_points(state, interior) {
+                        for (col, row) in state.mirror_positions(position) {
+                            canvas.fill_rect(
+                                (224, 160, 224, 255),
                                 Rect::new(
                                     (col * scale) as i32,
                                     (row * scale) as i32,
@@ -452,15 +983,41 @@ impl GuiElement<EditorState, ()> for ImageCanvas {
                         }
                     }
                 }
+                draw_preview(&mut canvas, state, outline, scale);
+            }
+        } else if state.tool() == Tool::Polygon {
+            if !self.polygon_points.is_empty() {
+                let outline = polyline(&self.polygon_points);
+                draw_preview(&mut canvas, state, outline, scale);
+                draw_handle(&mut canvas, self.polygon_points[0], scale);
+            }
+        } else if state.tool() == Tool::Curve {
+            if !self.curve_points.is_empty() {
+                let outline = bezier_curve(&self.curve_points);
+                draw_preview(&mut canvas, state, outline, scale);
+                let num_points = self.curve_points.len();
+                if num_points > 2 {
+                    let start = self.curve_points[0];
+                    let ctrl1 = self.curve_points[1];
+                    let ctrl2 = self.curve_points[num_points - 2];
+                    let end = self.curve_points[num_points - 1];
+                    draw_guide(&mut canvas, start, ctrl1, scale);
+                    draw_guide(&mut canvas, end, ctrl2, scale);
+                }
+                for &point in self.curve_points.iter() {
+                    draw_handle(&mut canvas, point, scale);
+                }
+            }
+        } else if state.tool() == Tool::Select {
+            if let Some(rect) = self.dragged_rect(state) {
+                let marquee_rect = Rect::new(
+                    rect.x() * (scale as i32),
+                    rect.y() * (scale as i32),
+                    rect.width() * scale,
+                    rect.height() * scale,
+                );
+                draw_marquee(&mut canvas, marquee_rect, 0);
             }
-        } else if let Some(rect) = self.dragged_rect(state) {
-            let marquee_rect = Rect::new(
-                rect.x() * (scale as i32),
-                rect.y() * (scale as i32),
-                rect.width() * scale,
-                rect.height() * scale,
-            );
-            draw_marquee(&mut canvas, marquee_rect, 0);
         } else if state.tool() == Tool::Lasso {
             for &(x, y) in self.lasso_points.iter() {
                 canvas.draw_rect(
@@ -474,29 +1031,9 @@ impl GuiElement<EditorState, ()> for ImageCanvas {
                 );
             }
         }
-        let (grid_horz, grid_vert) = state.grid();
-        if grid_horz > 0 {
-            let width = state.image().width();
-            let mut x = grid_horz;
-            while x < width {
-                canvas.draw_rect(
-                    GRID_COLOR,
-                    Rect::new((x * scale) as i32, 0, 1, canvas_rect.height()),
-                );
-                x += grid_horz;
-            }
-        }
-        if grid_vert > 0 {
-            let height = state.image().height();
-            let mut y = grid_vert;
-            while y < height {
-                canvas.draw_rect(
-                    GRID_COLOR,
-                    Rect::new(0, (y * scale) as i32, canvas_rect.width(), 1),
-                );
-                y += grid_vert;
-            }
-        }
+        draw_grid(&mut canvas, state.grid(), state.image_size(), scale);
+        draw_guide_lines(&mut canvas, state.guides(), scale);
+        draw_mirror_axes(&mut canvas, state, scale);
     }
 
     fn on_event(
@@ -506,6 +1043,16 @@ impl GuiElement<EditorState, ()> for ImageCanvas {
     ) -> Action<()> {
         match event {
             &Event::ClockTick => {
+                if self.polygon_click_counter > 0 {
+                    self.polygon_click_counter -= 1;
+                }
+                if state.tool() != Tool::Polygon {
+                    self.polygon_points.clear();
+                }
+                if state.tool() != Tool::Curve {
+                    self.curve_points.clear();
+                    self.curve_drag = None;
+                }
                 if state.selection().is_some() {
                     self.selection_animation_counter = mod_floor(
                         self.selection_animation_counter + 1,
@@ -517,6 +1064,9 @@ impl GuiElement<EditorState, ()> for ImageCanvas {
                 }
             }
             &Event::KeyDown(Keycode::Backspace, _) => {
+                if !state.active_layer_editable() {
+                    return Action::ignore();
+                }
                 if state.selection().is_some() {
                     state.mutation().delete_selection();
                     return Action::redraw().and_stop();
@@ -528,15 +1078,54 @@ impl GuiElement<EditorState, ()> for ImageCanvas {
                 if state.selection().is_some() {
                     state.mutation().unselect();
                     return Action::redraw().and_stop();
+                } else if self.cancel_path() {
+                    return Action::redraw().and_stop();
                 } else {
                     return Action::ignore();
                 }
             }
-            &Event::MouseDown(pt) => {
+            &Event::KeyDown(Keycode::Return, _) => {
+                if !state.active_layer_editable() {
+                    return Action::ignore();
+                }
+                let changed = match state.tool() {
+                    Tool::Curve => self.try_draw_curve(state),
+                    Tool::Polygon => self.try_draw_polygon(state),
+                    _ => false,
+                };
+                if changed {
+                    return Action::redraw().and_stop();
+                } else {
+                    return Action::ignore();
+                }
+            }
+            &Event::KeyDown(Keycode::Tab, _) => {
+                if state.tool() == Tool::Curve && self.toggle_curve_degree() {
+                    return Action::redraw().and_stop();
+                } else {
+                    return Action::ignore();
+                }
+            }
+            &Event::MouseDown(pt, kmod) => {
                 if self.rect(state).contains_point(pt) {
+                    if !state.active_layer_editable()
+                        && state.tool() != Tool::Eyedropper
+                        && state.tool() != Tool::Measure
+                    {
+                        return Action::ignore().and_stop();
+                    }
                     match state.tool() {
-                        Tool::Checkerboard => {
-                            let changed = self.try_checker_fill(pt, state);
+                        Tool::Curve => {
+                            let changed = self.start_curve(pt, state);
+                            return Action::redraw_if(changed).and_stop();
+                        }
+                        Tool::DitherBrush => {
+                            state.reset_persistent_mutation();
+                            let changed = self.try_dither_brush(pt, state);
+                            return Action::redraw_if(changed).and_stop();
+                        }
+                        Tool::DitherFill => {
+                            let changed = self.try_dither_fill(pt, state);
                             return Action::redraw_if(changed).and_stop();
                         }
                         Tool::Eyedropper => {
@@ -547,14 +1136,27 @@ impl GuiElement<EditorState, ()> for ImageCanvas {
                             let changed = self.try_lasso(pt, state);
                             return Action::redraw_if(changed).and_stop();
                         }
-                        Tool::Line | Tool::Oval | Tool::Rectangle => {
+                        Tool::FilledOval
+                        | Tool::FilledRectangle
+                        | Tool::Gradient
+                        | Tool::Line
+                        | Tool::Oval
+                        | Tool::Rectangle => {
                             self.drag_from_to = Some(ImageCanvasDrag {
                                 from_selection: Point::new(0, 0),
                                 from_pixel: pt,
                                 to_pixel: pt,
+                                keymod: kmod,
                             });
                             return Action::redraw().and_stop();
                         }
+                        Tool::Measure => {
+                            let (col, row) =
+                                self.clamp_mouse_to_row_col(pt, state);
+                            let point = (col as i32, row as i32);
+                            self.measure_points = Some((point, point));
+                            return Action::redraw().and_stop();
+                        }
                         Tool::PaintBucket => {
                             let changed = self.try_flood_fill(pt, state);
                             return Action::redraw_if(changed).and_stop();
@@ -571,9 +1173,14 @@ impl GuiElement<EditorState, ()> for ImageCanvas {
                         }
                         Tool::Pencil => {
                             state.reset_persistent_mutation();
+                            self.pencil_trail.clear();
                             let changed = self.try_pencil(pt, state);
                             return Action::redraw_if(changed).and_stop();
                         }
+                        Tool::Polygon => {
+                            let changed = self.add_polygon_vertex(pt, state);
+                            return Action::redraw_if(changed).and_stop();
+                        }
                         Tool::Select => {
                             let rect = state.selection_rect();
                             if let Some(rect) = rect {
@@ -602,13 +1209,14 @@ impl GuiElement<EditorState, ()> for ImageCanvas {
                                 },
                                 from_pixel: pt,
                                 to_pixel: pt,
+                                keymod: kmod,
                             });
                             return Action::redraw().and_stop();
                         }
-                        Tool::Watercolor => {
+                        Tool::Shade => {
                             state.reset_persistent_mutation();
-                            self.start_watercolor(pt, state);
-                            let changed = self.try_watercolor(pt, state);
+                            self.shaded_pixels.clear();
+                            let changed = self.try_shade(pt, state, kmod);
                             return Action::redraw_if(changed).and_stop();
                         }
                     }
@@ -618,6 +1226,24 @@ impl GuiElement<EditorState, ()> for ImageCanvas {
             }
             &Event::MouseUp => {
                 match state.tool() {
+                    Tool::Curve => {
+                        let changed = self.finish_curve_drag();
+                        return Action::redraw_if(changed);
+                    }
+                    Tool::FilledOval => {
+                        let shape = Shape::FilledOval;
+                        let changed = self.try_draw_shape(shape, state);
+                        return Action::redraw_if(changed);
+                    }
+                    Tool::FilledRectangle => {
+                        let shape = Shape::FilledRect;
+                        let changed = self.try_draw_shape(shape, state);
+                        return Action::redraw_if(changed);
+                    }
+                    Tool::Gradient => {
+                        let changed = self.try_gradient_fill(state);
+                        return Action::redraw_if(changed);
+                    }
                     Tool::Lasso => {
                         if !self.lasso_points.is_empty() {
                             if state.selection().is_none() {
@@ -654,14 +1280,42 @@ impl GuiElement<EditorState, ()> for ImageCanvas {
                 }
                 self.drag_from_to = None;
             }
-            &Event::MouseDrag(pt) => match state.tool() {
+            &Event::MouseDrag(pt, kmod) => match state.tool() {
+                Tool::DitherBrush => {
+                    let changed = self.try_dither_brush(pt, state);
+                    return Action::redraw_if(changed);
+                }
+                Tool::Measure => {
+                    let (col, row) = self.clamp_mouse_to_row_col(pt, state);
+                    if let Some((_, ref mut end)) = self.measure_points {
+                        if *end != (col as i32, row as i32) {
+                            *end = (col as i32, row as i32);
+                            return Action::redraw();
+                        }
+                    }
+                }
+                Tool::Curve => {
+                    if let Some(index) = self.curve_drag {
+                        let vertex = self.mouse_to_vertex(pt, state);
+                        if self.curve_points[index] != vertex {
+                            self.curve_points[index] = vertex;
+                            return Action::redraw();
+                        }
+                    }
+                }
                 Tool::Lasso => {
                     let changed = self.try_lasso(pt, state);
                     return Action::redraw_if(changed);
                 }
-                Tool::Line | Tool::Oval | Tool::Rectangle => {
+                Tool::FilledOval
+                | Tool::FilledRectangle
+                | Tool::Gradient
+                | Tool::Line
+                | Tool::Oval
+                | Tool::Rectangle => {
                     if let Some(ref mut drag) = self.drag_from_to {
                         drag.to_pixel = pt;
+                        drag.keymod = kmod;
                         return Action::redraw();
                     }
                 }
@@ -669,6 +1323,15 @@ impl GuiElement<EditorState, ()> for ImageCanvas {
                     let changed = self.try_pencil(pt, state);
                     return Action::redraw_if(changed);
                 }
+                Tool::Polygon => {
+                    let vertex = self.mouse_to_vertex(pt, state);
+                    if let Some(last) = self.polygon_points.last_mut() {
+                        if *last != vertex {
+                            *last = vertex;
+                            return Action::redraw();
+                        }
+                    }
+                }
                 Tool::Select => {
                     let scale = self.scale(state) as i32;
                     if let Some(ref mut drag) = self.drag_from_to {
@@ -683,8 +1346,8 @@ impl GuiElement<EditorState, ()> for ImageCanvas {
                         return Action::redraw();
                     }
                 }
-                Tool::Watercolor => {
-                    let changed = self.try_watercolor(pt, state);
+                Tool::Shade => {
+                    let changed = self.try_shade(pt, state, kmod);
                     return Action::redraw_if(changed);
                 }
                 _ => {}
@@ -706,9 +1369,484 @@ fn bresenham_shape(
 ) -> Vec<(i32, i32)> {
     match shape {
         Shape::Line => bresenham_line(x1, y1, x2, y2),
-        Shape::Oval => bresenham_oval(x1, y1, x2, y2),
-        Shape::Rect => bresenham_rect(x1, y1, x2, y2),
+        Shape::Oval | Shape::FilledOval => bresenham_oval(x1, y1, x2, y2),
+        Shape::Rect | Shape::FilledRect => bresenham_rect(x1, y1, x2, y2),
+    }
+}
+
+fn square_delta(dx: i32, dy: i32) -> (i32, i32) {
+    let side = cmp::max(dx.abs(), dy.abs());
+    let x_sign = if dx < 0 { -1 } else { 1 };
+    let y_sign = if dy < 0 { -1 } else { 1 };
+    (x_sign * side, y_sign * side)
+}
+
+fn snap_line_delta(dx: i32, dy: i32) -> (i32, i32) {
+    // Snap to whichever of these directions is closest to the dragged line;
+    // the 2:1 and 1:2 slopes give the clean stair-steps used in pixel art.
+    const DIRECTIONS: &[(i32, i32)] =
+        &[(1, 0), (2, 1), (1, 1), (1, 2), (0, 1)];
+    let (abs_dx, abs_dy) = (dx.abs() as f64, dy.abs() as f64);
+    let length = (abs_dx * abs_dx + abs_dy * abs_dy).sqrt();
+    if length == 0.0 {
+        return (0, 0);
+    }
+    let mut best = (1, 0);
+    let mut best_cos = -1.0;
+    for &(a, b) in DIRECTIONS {
+        let norm = ((a * a + b * b) as f64).sqrt();
+        let cos = (abs_dx * a as f64 + abs_dy * b as f64) / (norm * length);
+        if cos > best_cos {
+            best_cos = cos;
+            best = (a, b);
+        }
+    }
+    let (a, b) = best;
+    let steps = ((abs_dx * a as f64 + abs_dy * b as f64)
+        / ((a * a + b * b) as f64))
+        .round() as i32;
+    (dx.signum() * a * steps, dy.signum() * b * steps)
+}
+
+fn shape_interior(outline: &[(i32, i32)]) -> Vec<(i32, i32)> {
+    let outline_set: HashSet<(i32, i32)> = outline.iter().cloned().collect();
+    let mut rows: Vec<(i32, i32, i32)> = Vec::new();
+    for &(x, y) in outline.iter() {
+        match rows.iter_mut().find(|row| row.0 == y) {
+            Some(row) => {
+                row.1 = cmp::min(row.1, x);
+                row.2 = cmp::max(row.2, x);
+            }
+            None => rows.push((y, x, x)),
+        }
+    }
+    let mut output = Vec::new();
+    for (y, min_x, max_x) in rows {
+        for x in (min_x + 1)..max_x {
+            if !outline_set.contains(&(x, y)) {
+                output.push((x, y));
+            }
+        }
+    }
+    output
+}
+
+/// Converts points to image positions, dropping those that fall outside the
+/// image, or wrapping them around the edges in seamless mode.
+fn image_points(
+    state: &EditorState,
+    points: Vec<(i32, i32)>,
+) -> Vec<(u32, u32)> {
+    let size = state.image_size();
+    if state.seamless() {
+        wrap_points(points, size)
+    } else {
+        clip_points(points, size)
+    }
+}
+
+fn wrap_points(
+    points: Vec<(i32, i32)>,
+    (width, height): (u32, u32),
+) -> Vec<(u32, u32)> {
+    points
+        .into_iter()
+        .map(|(x, y)| {
+            let x = mod_floor(x, width as i32) as u32;
+            let y = mod_floor(y, height as i32) as u32;
+            (x, y)
+        })
+        .collect()
+}
+
+fn clip_points(
+    points: Vec<(i32, i32)>,
+    (width, height): (u32, u32),
+) -> Vec<(u32, u32)> {
+    points
+        .into_iter()
+        .filter(|&(x, y)| {
+            x >= 0 && (x as u32) < width && y >= 0 && (y as u32) < height
+        })
+        .map(|(x, y)| (x as u32, y as u32))
+        .collect()
+}
+
+fn gradient_colors(state: &EditorState, use_ramp: bool) -> Vec<Option<Color>> {
+    let primary = state.color();
+    let secondary = match state.secondary_color() {
+        Some(secondary) if use_ramp => secondary,
+        secondary => return vec![Some(primary), secondary],
+    };
+    // Follow a document ramp containing both colors if there is one, or else
+    // walk the palette indices between them.
+    let (ramp, from, to) = match state.ramp_containing(primary) {
+        Some(ramp) if ramp.contains(&secondary) => (
+            ramp.to_vec(),
+            ramp.iter().position(|&c| c == primary).unwrap(),
+            ramp.iter().position(|&c| c == secondary).unwrap(),
+        ),
+        _ => (
+            (0..16).map(util::color_at_index).collect(),
+            util::color_index(primary),
+            util::color_index(secondary),
+        ),
+    };
+    let mut colors: Vec<Option<Color>> = ramp
+        [cmp::min(from, to)..(cmp::max(from, to) + 1)]
+        .iter()
+        .map(|&color| Some(color))
+        .collect();
+    if from > to {
+        colors.reverse();
+    }
+    colors
+}
+
+fn contiguous_region(
+    image: &Image,
+    start: (u32, u32),
+    wrap: bool,
+) -> Vec<(u32, u32)> {
+    let width = image.width();
+    let height = image.height();
+    let from_color = image[start];
+    let mut region: HashSet<(u32, u32)> = HashSet::new();
+    region.insert(start);
+    let mut stack: Vec<(u32, u32)> = vec![start];
+    while let Some((col, row)) = stack.pop() {
+        let mut next: Vec<(u32, u32)> = vec![];
+        if col > 0 {
+            next.push((col - 1, row));
+        } else if wrap {
+            next.push((width - 1, row));
+        }
+        if col < width - 1 {
+            next.push((col + 1, row));
+        } else if wrap {
+            next.push((0, row));
+        }
+        if row > 0 {
+            next.push((col, row - 1));
+        } else if wrap {
+            next.push((col, height - 1));
+        }
+        if row < height - 1 {
+            next.push((col, row + 1));
+        } else if wrap {
+            next.push((col, 0));
+        }
+        for coords in next {
+            if image[coords] == from_color && region.insert(coords) {
+                stack.push(coords);
+            }
+        }
+    }
+    region.into_iter().collect()
+}
+
+fn dither_changes(
+    state: &EditorState,
+    positions: Vec<(u32, u32)>,
+) -> Vec<((u32, u32), Color)> {
+    let dither = state.dither();
+    let primary = state.color();
+    let secondary = state.secondary_color();
+    let image = state.image();
+    positions
+        .into_iter()
+        .filter_map(|position| {
+            dither
+                .color_at(position, primary, secondary)
+                .filter(|&color| image[position] != color)
+                .map(|color| (position, color))
+        })
+        .collect()
+}
+
+fn is_l_corner(
+    (x0, y0): (u32, u32),
+    (x1, y1): (u32, u32),
+    (x2, y2): (u32, u32),
+) -> bool {
+    let orthogonal = |ax: u32, ay: u32, bx: u32, by: u32| {
+        (ax as i32 - bx as i32).abs() + (ay as i32 - by as i32).abs() == 1
+    };
+    orthogonal(x0, y0, x1, y1)
+        && orthogonal(x1, y1, x2, y2)
+        && x0 != x2
+        && y0 != y2
+}
+
+fn interpolate(from: (i32, i32), to: (i32, i32), t: f64) -> (i32, i32) {
+    let x = from.0 as f64 + (to.0 - from.0) as f64 * t;
+    let y = from.1 as f64 + (to.1 - from.1) as f64 * t;
+    (x.round() as i32, y.round() as i32)
+}
+
+fn polyline(vertices: &[(i32, i32)]) -> Vec<(i32, i32)> {
+    let mut output: Vec<(i32, i32)> =
+        vertices.iter().take(1).cloned().collect();
+    for pair in vertices.windows(2) {
+        let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+        output.extend(bresenham_line(x0, y0, x1, y1).into_iter().skip(1));
+    }
+    output
+}
+
+fn bezier_curve(points: &[(i32, i32)]) -> Vec<(i32, i32)> {
+    let mut output = Vec::new();
+    match *points {
+        [p0, p1, p2] => bresenham_quad_bezier(p0, p1, p2, &mut output),
+        [p0, p1, p2, p3] => {
+            bresenham_cubic_bezier(p0, p1, p2, p3, &mut output)
+        }
+        [(x0, y0), .., (x1, y1)] => {
+            output = bresenham_line(x0, y0, x1, y1);
+        }
+        _ => output.extend_from_slice(points),
     }
+    output.dedup();
+    output
+}
+
+fn bresenham_cubic_bezier(
+    p0: (i32, i32),
+    p1: (i32, i32),
+    p2: (i32, i32),
+    p3: (i32, i32),
+    output: &mut Vec<(i32, i32)>,
+) {
+    // This function was adapted from the plotCubicBezier function in
+    // http://members.chello.at/easyfilter/bresenham.js by Zingl Alois.  It
+    // splits the curve wherever its gradient changes sign, and steps each
+    // piece with bresenham_cubic_bezier_segment.
+    let (x0, y0) = (p0.0 as f64, p0.1 as f64);
+    let (x1, y1) = (p1.0 as f64, p1.1 as f64);
+    let (x2, y2) = (p2.0 as f64, p2.1 as f64);
+    let (x3, y3) = (p3.0 as f64, p3.1 as f64);
+    let xc = x0 + x1 - x2 - x3;
+    let xa = xc - 4.0 * (x1 - x2);
+    let xb = x0 - x1 - x2 + x3;
+    let xd = xb + 4.0 * (x1 + x2);
+    let yc = y0 + y1 - y2 - y3;
+    let ya = yc - 4.0 * (y1 - y2);
+    let yb = y0 - y1 - y2 + y3;
+    let yd = yb + 4.0 * (y1 + y2);
+    let mut cuts = Vec::<f64>::with_capacity(5);
+    for &(a, b, c) in &[(xa, xb, xc), (ya, yb, yc)] {
+        let discriminant = b * b - a * c;
+        if a == 0.0 {
+            if c.abs() < 2.0 * b.abs() {
+                cuts.push(c / (2.0 * b));
+            }
+        } else if discriminant > 0.0 {
+            let root = discriminant.sqrt();
+            for &t in &[(b - root) / a, (b + root) / a] {
+                if t.abs() < 1.0 {
+                    cuts.push(t);
+                }
+            }
+        }
+    }
+    cuts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    cuts.push(1.0);
+    let (mut px, mut py) = p0;
+    let (mut fx0, mut fy0) = (x0, y0);
+    let mut t1 = -1.0;
+    for t2 in cuts {
+        let mut fx1 = (t1 * (t1 * xb - 2.0 * xc)
+            - t2 * (t1 * (t1 * xa - 2.0 * xb) + xc)
+            + xd)
+            / 8.0
+            - fx0;
+        let mut fy1 = (t1 * (t1 * yb - 2.0 * yc)
+            - t2 * (t1 * (t1 * ya - 2.0 * yb) + yc)
+            + yd)
+            / 8.0
+            - fy0;
+        let mut fx2 = (t2 * (t2 * xb - 2.0 * xc)
+            - t1 * (t2 * (t2 * xa - 2.0 * xb) + xc)
+            + xd)
+            / 8.0
+            - fx0;
+        let mut fy2 = (t2 * (t2 * yb - 2.0 * yc)
+            - t1 * (t2 * (t2 * ya - 2.0 * yb) + yc)
+            + yd)
+            / 8.0
+            - fy0;
+        let fx3 = (t2 * (t2 * (3.0 * xb - t2 * xa) - 3.0 * xc) + xd) / 8.0;
+        let fy3 = (t2 * (t2 * (3.0 * yb - t2 * ya) - 3.0 * yc) + yd) / 8.0;
+        fx0 -= fx3;
+        fy0 -= fy3;
+        let qx = (fx3 + 0.5).floor() as i32;
+        let qy = (fy3 + 0.5).floor() as i32;
+        if fx0 != 0.0 {
+            fx0 = (px - qx) as f64 / fx0;
+            fx1 *= fx0;
+            fx2 *= fx0;
+        }
+        if fy0 != 0.0 {
+            fy0 = (py - qy) as f64 / fy0;
+            fy1 *= fy0;
+            fy2 *= fy0;
+        }
+        if px != qx || py != qy {
+            bresenham_cubic_bezier_segment(
+                (px, py),
+                (px as f64 + fx1, py as f64 + fy1),
+                (px as f64 + fx2, py as f64 + fy2),
+                (qx, qy),
+                output,
+            );
+        }
+        px = qx;
+        py = qy;
+        fx0 = fx3;
+        fy0 = fy3;
+        t1 = t2;
+    }
+}
+
+fn bresenham_cubic_bezier_segment(
+    p0: (i32, i32),
+    p1: (f64, f64),
+    p2: (f64, f64),
+    p3: (i32, i32),
+    output: &mut Vec<(i32, i32)>,
+) {
+    // This function was adapted from the plotCubicBezierSeg function in
+    // http://members.chello.at/easyfilter/bresenham.js by Zingl Alois.  It
+    // steps in from both ends of the segment in turn, and finishes with a
+    // straight line in case of a cusp or self-intersection.
+    let ((mut x0, mut y0), (mut x3, mut y3)) = (p0, p3);
+    let (x1, y1) = p1;
+    let (x2, y2) = p2;
+    let (fx0, fy0, fx3, fy3) = (x0 as f64, y0 as f64, x3 as f64, y3 as f64);
+    let mut sx: i32 = if x0 < x3 { 1 } else { -1 };
+    let mut sy: i32 = if y0 < y3 { 1 } else { -1 };
+    let xc = -(fx0 + x1 - x2 - fx3).abs();
+    let xa = xc - 4.0 * sx as f64 * (x1 - x2);
+    let mut xb = sx as f64 * (fx0 - x1 - x2 + fx3);
+    let yc = -(fy0 + y1 - y2 - fy3).abs();
+    let ya = yc - 4.0 * sy as f64 * (y1 - y2);
+    let mut yb = sy as f64 * (fy0 - y1 - y2 + fy3);
+    if xa == 0.0 && ya == 0.0 {
+        let mx = ((3.0 * x1 - fx0 + 1.0) / 2.0).floor() as i32;
+        let my = ((3.0 * y1 - fy0 + 1.0) / 2.0).floor() as i32;
+        bresenham_quad_bezier_segment((x0, y0), (mx, my), (x3, y3), output);
+        return;
+    }
+    let mut len1 = (x1 - fx0) * (x1 - fx0) + (y1 - fy0) * (y1 - fy0) + 1.0;
+    let len2 = (x2 - fx3) * (x2 - fx3) + (y2 - fy3) * (y2 - fy3) + 1.0;
+    for _leg in 0..2 {
+        let mut ab = xa * yb - xb * ya;
+        let mut ac = xa * yc - xc * ya;
+        let mut bc = xb * yc - xc * yb;
+        let mut ex = ab * (ab + ac - 3.0 * bc) + ac * ac;
+        let f = if ex > 0.0 { 1.0 } else { (1.0 + 1024.0 / len1).sqrt() };
+        ab *= f;
+        ac *= f;
+        bc *= f;
+        ex *= f * f;
+        let mut xy = 9.0 * (ab + ac + bc) / 8.0;
+        let mut cb = 8.0 * (xa - ya);
+        let mut dx = 27.0
+            * (8.0 * ab * (yb * yb - ya * yc) + ex * (ya + 2.0 * yb + yc))
+            / 64.0
+            - ya * ya * (xy - ya);
+        let mut dy = 27.0
+            * (8.0 * ab * (xb * xb - xa * xc) - ex * (xa + 2.0 * xb + xc))
+            / 64.0
+            - xa * xa * (xy + xa);
+        let mut xx = 3.0
+            * (3.0 * ab * (3.0 * yb * yb - ya * ya - 2.0 * ya * yc)
+                - ya * (3.0 * ac * (ya + yb) + ya * cb))
+            / 4.0;
+        let mut yy = 3.0
+            * (3.0 * ab * (3.0 * xb * xb - xa * xa - 2.0 * xa * xc)
+                - xa * (3.0 * ac * (xa + xb) + xa * cb))
+            / 4.0;
+        xy = xa * ya * (6.0 * ab + 6.0 * ac - 3.0 * bc + cb);
+        ac = ya * ya;
+        cb = xa * xa;
+        xy = 3.0
+            * (xy + 9.0 * f * (cb * yb * yc - xb * xc * ac)
+                - 18.0 * xb * yb * ab)
+            / 8.0;
+        if ex < 0.0 {
+            dx = -dx;
+            dy = -dy;
+            xx = -xx;
+            yy = -yy;
+            xy = -xy;
+            ac = -ac;
+            cb = -cb;
+        }
+        ab = 6.0 * ya * ac;
+        ac *= -6.0 * xa;
+        bc = 6.0 * ya * cb;
+        cb *= -6.0 * xa;
+        dx += xy;
+        ex = dx + dy;
+        dy += xy;
+        let mut pxy = false;
+        let (mut fx, mut fy) = (f, f);
+        'pixels: while x0 != x3 && y0 != y3 {
+            output.push((x0, y0));
+            loop {
+                if !pxy && (dx > xy || dy < xy) {
+                    break 'pixels;
+                }
+                if pxy && (dx > 0.0 || dy < 0.0) {
+                    break 'pixels;
+                }
+                let y_test = 2.0 * ex - dy;
+                if 2.0 * ex >= dx {
+                    fx -= 1.0;
+                    dx += xx;
+                    ex += dx;
+                    xy += ac;
+                    dy += xy;
+                    yy += bc;
+                    xx += ab;
+                } else if y_test > 0.0 {
+                    break 'pixels;
+                }
+                if y_test <= 0.0 {
+                    fy -= 1.0;
+                    dy += yy;
+                    ex += dy;
+                    xy += bc;
+                    dx += xy;
+                    xx += ac;
+                    yy += cb;
+                }
+                if fx <= 0.0 || fy <= 0.0 {
+                    break;
+                }
+            }
+            if 2.0 * fx <= f {
+                x0 += sx;
+                fx += f;
+            }
+            if 2.0 * fy <= f {
+                y0 += sy;
+                fy += f;
+            }
+            if !pxy && dx < 0.0 && dy > 0.0 {
+                pxy = true;
+            }
+        }
+        mem::swap(&mut x0, &mut x3);
+        mem::swap(&mut y0, &mut y3);
+        sx = -sx;
+        sy = -sy;
+        xb = -xb;
+        yb = -yb;
+        len1 = len2;
+    }
+    output.extend(bresenham_line(x0, y0, x3, y3));
 }
 
 fn bresenham_line(x0: i32, y0: i32, x1: i32, y1: i32) -> Vec<(i32, i32)> {
@@ -782,6 +1920,133 @@ fn bresenham_oval(x0: i32, y0: i32, x1: i32, y1: i32) -> Vec<(i32, i32)> {
     output
 }
 
+fn bresenham_quad_bezier(
+    p0: (i32, i32),
+    p1: (i32, i32),
+    p2: (i32, i32),
+    output: &mut Vec<(i32, i32)>,
+) {
+    // This function was adapted from the plotQuadBezier function in
+    // http://members.chello.at/easyfilter/bresenham.js by Zingl Alois.  It
+    // splits the curve wherever its gradient changes sign, so that each
+    // piece can be stepped by bresenham_quad_bezier_segment.
+    let ((mut x0, mut y0), (mut x1, mut y1), (mut x2, mut y2)) = (p0, p1, p2);
+    let round = |value: f64| (value + 0.5).floor() as i32;
+    let x = x0 - x1;
+    let y = y0 - y1;
+    if x * (x2 - x1) > 0 {
+        if y * (y2 - y1) > 0 {
+            let t = (x0 - 2 * x1 + x2) as f64;
+            if ((y0 - 2 * y1 + y2) as f64 / t * x as f64).abs()
+                > (y as f64).abs()
+            {
+                x0 = x2;
+                x2 = x + x1;
+                y0 = y2;
+                y2 = y + y1;
+            }
+        }
+        let t = (x0 - x1) as f64 / (x0 - 2 * x1 + x2) as f64;
+        let r = (1.0 - t) * ((1.0 - t) * y0 as f64 + 2.0 * t * y1 as f64)
+            + t * t * y2 as f64;
+        let t = (x0 * x2 - x1 * x1) as f64 * t / (x0 - x1) as f64;
+        let (x, y) = (round(t), round(r));
+        let r =
+            (y1 - y0) as f64 * (t - x0 as f64) / (x1 - x0) as f64 + y0 as f64;
+        bresenham_quad_bezier_segment((x0, y0), (x, round(r)), (x, y), output);
+        let r =
+            (y1 - y2) as f64 * (t - x2 as f64) / (x1 - x2) as f64 + y2 as f64;
+        x0 = x;
+        x1 = x;
+        y0 = y;
+        y1 = round(r);
+    }
+    if (y0 - y1) * (y2 - y1) > 0 {
+        let t = (y0 - y1) as f64 / (y0 - 2 * y1 + y2) as f64;
+        let r = (1.0 - t) * ((1.0 - t) * x0 as f64 + 2.0 * t * x1 as f64)
+            + t * t * x2 as f64;
+        let t = (y0 * y2 - y1 * y1) as f64 * t / (y0 - y1) as f64;
+        let (x, y) = (round(r), round(t));
+        let r =
+            (x1 - x0) as f64 * (t - y0 as f64) / (y1 - y0) as f64 + x0 as f64;
+        bresenham_quad_bezier_segment((x0, y0), (round(r), y), (x, y), output);
+        let r =
+            (x1 - x2) as f64 * (t - y2 as f64) / (y1 - y2) as f64 + x2 as f64;
+        x0 = x;
+        x1 = round(r);
+        y0 = y;
+        y1 = y;
+    }
+    bresenham_quad_bezier_segment((x0, y0), (x1, y1), (x2, y2), output);
+}
+
+fn bresenham_quad_bezier_segment(
+    p0: (i32, i32),
+    p1: (i32, i32),
+    p2: (i32, i32),
+    output: &mut Vec<(i32, i32)>,
+) {
+    // This function was adapted from the plotQuadBezierSeg function in
+    // http://members.chello.at/easyfilter/bresenham.js by Zingl Alois.  The
+    // sign of the curve's gradient must not change within the segment.
+    let ((mut x0, mut y0), (x1, y1), (mut x2, mut y2)) = (p0, p1, p2);
+    let (mut sx, mut sy) = ((x2 - x1) as i64, (y2 - y1) as i64);
+    let (mut xx, mut yy) = ((x0 - x1) as i64, (y0 - y1) as i64);
+    let mut cur = xx * sy - yy * sx;
+    if sx * sx + sy * sy > xx * xx + yy * yy {
+        x2 = x0;
+        x0 = sx as i32 + x1;
+        y2 = y0;
+        y0 = sy as i32 + y1;
+        cur = -cur;
+    }
+    if cur != 0 {
+        xx += sx;
+        sx = if x0 < x2 { 1 } else { -1 };
+        xx *= sx;
+        yy += sy;
+        sy = if y0 < y2 { 1 } else { -1 };
+        yy *= sy;
+        let mut xy = 2 * xx * yy;
+        xx *= xx;
+        yy *= yy;
+        if cur * sx * sy < 0 {
+            xx = -xx;
+            yy = -yy;
+            xy = -xy;
+            cur = -cur;
+        }
+        let mut dx = 4 * sy * cur * (x1 - x0) as i64 + xx - xy;
+        let mut dy = 4 * sx * cur * (y0 - y1) as i64 + yy - xy;
+        xx += xx;
+        yy += yy;
+        let mut err = dx + dy + xy;
+        loop {
+            output.push((x0, y0));
+            if x0 == x2 && y0 == y2 {
+                return;
+            }
+            let y_step = 2 * err < dx;
+            if 2 * err > dy {
+                x0 += sx as i32;
+                dx -= xy;
+                dy += yy;
+                err += dy;
+            }
+            if y_step {
+                y0 += sy as i32;
+                dy -= xy;
+                dx += xx;
+                err += dx;
+            }
+            if dy >= dx {
+                break;
+            }
+        }
+    }
+    output.extend(bresenham_line(x0, y0, x2, y2));
+}
+
 fn bresenham_rect(x0: i32, y0: i32, x1: i32, y1: i32) -> Vec<(i32, i32)> {
     let (x0, x1) = (cmp::min(x0, x1), cmp::max(x0, x1));
     let (y0, y1) = (cmp::min(y0, y1), cmp::max(y0, y1));
@@ -797,6 +2062,204 @@ fn bresenham_rect(x0: i32, y0: i32, x1: i32, y1: i32) -> Vec<(i32, i32)> {
     output
 }
 
+fn draw_preview(
+    canvas: &mut Canvas,
+    state: &EditorState,
+    points: Vec<(i32, i32)>,
+    scale: u32,
+) {
+    for position in image_points(state, points) {
+        for (col, row) in state.mirror_positions(position) {
+            canvas.draw_rect(
+                PREVIEW_COLOR,
+                Rect::new(
+                    (col * scale) as i32,
+                    (row * scale) as i32,
+                    scale,
+                    scale,
+                ),
+            );
+        }
+    }
+}
+
+fn draw_mirror_axes(canvas: &mut Canvas, state: &EditorState, scale: u32) {
+    let mirror = state.mirror();
+    if mirror == Mirror::None {
+        return;
+    }
+    let (axis_x, axis_y) = state.mirror_axes();
+    let center_x = (axis_x + 1) * (scale as i32) / 2;
+    let center_y = (axis_y + 1) * (scale as i32) / 2;
+    let (width, height) = canvas.size();
+    match mirror {
+        Mirror::Horz | Mirror::Both | Mirror::Kaleido => {
+            canvas.fill_rect(AXIS_COLOR, Rect::new(center_x, 0, 1, height));
+        }
+        _ => {}
+    }
+    match mirror {
+        Mirror::Vert | Mirror::Both | Mirror::Kaleido => {
+            canvas.fill_rect(AXIS_COLOR, Rect::new(0, center_y, width, 1));
+        }
+        _ => {}
+    }
+    let extent = (width + height) as i32;
+    for offset in (-extent..extent).step_by(2) {
+        if mirror == Mirror::Diag || mirror == Mirror::Kaleido {
+            let point = Point::new(center_x + offset, center_y + offset);
+            canvas.draw_pixel(AXIS_COLOR, point);
+        }
+        if mirror == Mirror::AntiDiag || mirror == Mirror::Kaleido {
+            let point = Point::new(center_x + offset, center_y - offset);
+            canvas.draw_pixel(AXIS_COLOR, point);
+        }
+    }
+    if mirror == Mirror::Rot2 || mirror == Mirror::Rot4 {
+        canvas.fill_rect(AXIS_COLOR, Rect::new(center_x - 2, center_y, 5, 1));
+        canvas.fill_rect(AXIS_COLOR, Rect::new(center_x, center_y - 2, 1, 5));
+    }
+}
+
+fn draw_handle(canvas: &mut Canvas, (col, row): (i32, i32), scale: u32) {
+    let rect = Rect::new(col * scale as i32, row * scale as i32, scale, scale);
+    canvas.draw_rect(HANDLE_COLOR, expand(rect, 1));
+}
+
+fn draw_guide(
+    canvas: &mut Canvas,
+    from: (i32, i32),
+    to: (i32, i32),
+    scale: u32,
+) {
+    let scale = scale as i32;
+    let half = scale / 2;
+    for (x, y) in bresenham_line(
+        from.0 * scale + half,
+        from.1 * scale + half,
+        to.0 * scale + half,
+        to.1 * scale + half,
+    ) {
+        canvas.draw_pixel(HANDLE_COLOR, Point::new(x, y));
+    }
+}
+
+fn draw_guide_lines(canvas: &mut Canvas, guides: &[Guide], scale: u32) {
+    let (width, height) = canvas.size();
+    if width == 0 || height == 0 {
+        return;
+    }
+    for &guide in guides {
+        // Keep guides on the far edges of the image visible.
+        match guide {
+            Guide::Horz(y) => {
+                let y = cmp::min(y * scale, height - 1) as i32;
+                canvas.fill_rect(GUIDE_COLOR, Rect::new(0, y, width, 1));
+            }
+            Guide::Vert(x) => {
+                let x = cmp::min(x * scale, width - 1) as i32;
+                canvas.fill_rect(GUIDE_COLOR, Rect::new(x, 0, 1, height));
+            }
+        }
+    }
+}
+
+fn draw_grid(
+    canvas: &mut Canvas,
+    grid: &Grid,
+    (width, height): (u32, u32),
+    scale: u32,
+) {
+    let canvas_width = width * scale;
+    let canvas_height = height * scale;
+    if grid.pixel_grid && scale >= PIXEL_GRID_MIN_SCALE {
+        for x in 1..width {
+            canvas.fill_rect(
+                PIXEL_GRID_COLOR,
+                Rect::new((x * scale) as i32, 0, 1, canvas_height),
+            );
+        }
+        for y in 1..height {
+            canvas.fill_rect(
+                PIXEL_GRID_COLOR,
+                Rect::new(0, (y * scale) as i32, canvas_width, 1),
+            );
+        }
+    }
+    let (r, g, b, a) = grid.color;
+    let line_color =
+        |major: bool| if major { (r, g, b, a) } else { (r, g, b, a / 2) };
+    for (x, major) in grid.columns(width) {
+        canvas.fill_rect(
+            line_color(major),
+            Rect::new((x * scale) as i32, 0, 1, canvas_height),
+        );
+    }
+    for (y, major) in grid.rows(height) {
+        canvas.fill_rect(
+            line_color(major),
+            Rect::new(0, (y * scale) as i32, canvas_width, 1),
+        );
+    }
+}
+
+fn draw_label(
+    canvas: &mut Canvas,
+    resources: &Resources,
+    topleft: Point,
+    label: &str,
+) {
+    canvas.fill_rect(
+        (255, 255, 255, 255),
+        Rect::new(
+            topleft.x() + 1,
+            topleft.y() - 11,
+            (resources.font().text_width(label) + 1) as u32,
+            10,
+        ),
+    );
+    canvas.draw_string(
+        resources.font(),
+        topleft.x() + 2,
+        topleft.y() - 11,
+        label,
+    );
+}
+
+fn draw_checkerboard(canvas: &mut Canvas, rect: Rect) {
+    let mut canvas = canvas.subcanvas(rect);
+    canvas.clear(CHECKERBOARD_LIGHT);
+    let size = CHECKERBOARD_SIZE as i32;
+    for row in 0..(rect.height() as i32 + size - 1) / size {
+        for col in 0..(rect.width() as i32 + size - 1) / size {
+            if (row + col) % 2 == 1 {
+                canvas.fill_rect(
+                    CHECKERBOARD_DARK,
+                    Rect::new(
+                        col * size,
+                        row * size,
+                        CHECKERBOARD_SIZE,
+                        CHECKERBOARD_SIZE,
+                    ),
+                );
+            }
+        }
+    }
+}
+
+// Returns the range of reference pixels (each `size` screen pixels wide,
+// starting at `start`) that fall within `extent` screen pixels.
+fn visible_range(
+    start: f64,
+    size: f64,
+    extent: u32,
+    count: u32,
+) -> Range<u32> {
+    let first = (-start / size).floor().max(0.0).min(count as f64) as u32;
+    let last = ((extent as f64 - start) / size).ceil().max(0.0);
+    first..(last.min(count as f64) as u32)
+}
+
 fn expand(rect: Rect, by: i32) -> Rect {
     Rect::new(
         rect.x() - by,
@@ -0,0 +1,112 @@
+// +--------------------------------------------------------------------------+
+// | Copyright 2016 Matthew D. Steele <mdsteele@alum.mit.edu>                 |
+// |                                                                          |
+// | This file is part of Tuna.                                               |
+// |                                                                          |
+// | Tuna is free software: you can redistribute it and/or modify it under    |
+// | the terms of the GNU General Public License as published by the Free     |
+// | Software Foundation, either version 3 of the License, or (at your        |
+// | option) any later version.                                               |
+// |                                                                          |
+// | Tuna is distributed in the hope that it will be useful, but WITHOUT ANY  |
+// | WARRANTY; without even the implied warranty of MERCHANTABILITY or        |
+// | FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License    |
+// | for details.                                                             |
+// |                                                                          |
+// | You should have received a copy of the GNU General Public License along  |
+// | with Tuna.  If not, see <http://www.gnu.org/licenses/>.                  |
+// +--------------------------------------------------------------------------+
+
+use crate::util;
+use std::io;
+use std::rc::Rc;
+
+//===========================================================================//
+
+// A full-color image shown underneath the canvas for tracing.  It is never
+// saved into the document.
+#[derive(Clone)]
+pub struct Reference {
+    width: u32,
+    height: u32,
+    rgba: Rc<Vec<u8>>,
+    pub opacity: u8,
+    pub offset: (i32, i32),
+    pub scale: f64,
+}
+
+impl Reference {
+    pub fn load(path: &String) -> io::Result<Reference> {
+        let (width, height, rgba) = util::load_png_rgba_from_file(path)?;
+        Ok(Reference {
+            width,
+            height,
+            rgba: Rc::new(rgba),
+            opacity: 50,
+            offset: (0, 0),
+            scale: 1.0,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the color of the given pixel, with its alpha scaled by the
+    /// reference's opacity (a percentage).
+    pub fn pixel(&self, col: u32, row: u32) -> (u8, u8, u8, u8) {
+        let start = ((row * self.width + col) as usize) * 4;
+        let rgba = &self.rgba[start..(start + 4)];
+        let alpha = (rgba[3] as u32) * (self.opacity as u32) / 100;
+        (rgba[0], rgba[1], rgba[2], alpha as u8)
+    }
+
+    /// Parses settings of the form "50% 3,-2 x0.25", where each part is
+    /// optional: the opacity percentage, the offset (in image pixels) of the
+    /// reference's top-left corner, and the number of image pixels per
+    /// reference pixel.
+    pub fn parse_settings(&mut self, text: &str) -> bool {
+        let mut opacity = self.opacity;
+        let mut offset = self.offset;
+        let mut scale = self.scale;
+        for token in text.split_whitespace() {
+            if let Some(percent) = token.strip_suffix('%') {
+                match percent.parse::<u8>() {
+                    Ok(percent) if percent <= 100 => opacity = percent,
+                    _ => return false,
+                }
+            } else if let Some(scale_text) = token.strip_prefix('x') {
+                match scale_text.parse::<f64>() {
+                    Ok(value) if value > 0.0 => scale = value,
+                    _ => return false,
+                }
+            } else {
+                let pieces: Vec<&str> = token.split(',').collect();
+                if pieces.len() != 2 {
+                    return false;
+                }
+                match (pieces[0].parse::<i32>(), pieces[1].parse::<i32>()) {
+                    (Ok(x), Ok(y)) => offset = (x, y),
+                    _ => return false,
+                }
+            }
+        }
+        self.opacity = opacity;
+        self.offset = offset;
+        self.scale = scale;
+        true
+    }
+
+    pub fn settings_string(&self) -> String {
+        format!(
+            "{}% {},{} x{}",
+            self.opacity, self.offset.0, self.offset.1, self.scale
+        )
+    }
+}
+
+//===========================================================================//
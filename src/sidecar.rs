@@ -0,0 +1,220 @@
+// +--------------------------------------------------------------------------+
+// | Copyright 2016 Matthew D. Steele <mdsteele@alum.mit.edu>                 |
+// |                                                                          |
+// | This file is part of Tuna.                                               |
+// |                                                                          |
+// | Tuna is free software: you can redistribute it and/or modify it under    |
+// | the terms of the GNU General Public License as published by the Free     |
+// | Software Foundation, either version 3 of the License, or (at your        |
+// | option) any later version.                                               |
+// |                                                                          |
+// | Tuna is distributed in the hope that it will be useful, but WITHOUT ANY  |
+// | WARRANTY; without even the implied warranty of MERCHANTABILITY or        |
+// | FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License    |
+// | for details.                                                             |
+// |                                                                          |
+// | You should have received a copy of the GNU General Public License along  |
+// | with Tuna.  If not, see <http://www.gnu.org/licenses/>.                  |
+// +--------------------------------------------------------------------------+
+
+use crate::grid::{Grid, Guide};
+use crate::util;
+use ahi::{Collection, Color, Image};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+
+//===========================================================================//
+
+// Editor settings that belong to a document but can't be stored in the AHI
+// or AHF file itself are kept in a plain-text file next to it.
+#[derive(Default)]
+pub struct Sidecar {
+    pub ramps: Vec<Vec<Color>>,
+    pub grid: Option<Grid>,
+    pub guides: Vec<Guide>,
+    pub layers: Vec<LayerInfo>,
+}
+
+// Describes one layer; the layer images themselves are stored, in the same
+// order, in a separate AHI file next to the document.
+#[derive(Clone)]
+pub struct LayerInfo {
+    pub image_index: usize,
+    pub visible: bool,
+    pub locked: bool,
+    pub active: bool,
+}
+
+impl Sidecar {
+    pub fn load(document_path: &str) -> io::Result<Sidecar> {
+        let file = match File::open(sidecar_path(document_path)) {
+            Ok(file) => file,
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => {
+                return Ok(Sidecar::default());
+            }
+            Err(error) => return Err(error),
+        };
+        let mut sidecar = Sidecar::default();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if sidecar.parse_line(&line).is_none() {
+                println!("Ignoring invalid setting: {:?}", line);
+            }
+        }
+        Ok(sidecar)
+    }
+
+    fn parse_line(&mut self, line: &str) -> Option<()> {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("ramp") => self.ramps.push(parse_ramp(words.next()?)?),
+            Some("grid") => {
+                let text = words.collect::<Vec<&str>>().join(" ");
+                self.grid = Some(Grid::parse(&text)?);
+            }
+            Some("guide") => {
+                self.guides.push(parse_guide(words.next(), words.next())?);
+            }
+            Some("layer") => {
+                let info = parse_layer_info(words.next(), words.next())?;
+                self.layers.push(info);
+            }
+            _ => {}
+        }
+        Some(())
+    }
+
+    pub fn save(&self, document_path: &str) -> io::Result<()> {
+        let path = sidecar_path(document_path);
+        if self.is_empty() {
+            return match fs::remove_file(&path) {
+                Err(ref error) if error.kind() == io::ErrorKind::NotFound => {
+                    Ok(())
+                }
+                result => result,
+            };
+        }
+        let mut file = File::create(&path)?;
+        for ramp in self.ramps.iter() {
+            writeln!(file, "ramp {}", format_ramp(ramp))?;
+        }
+        if let Some(ref grid) = self.grid {
+            writeln!(file, "grid {}", grid.format())?;
+        }
+        for guide in self.guides.iter() {
+            match *guide {
+                Guide::Horz(y) => writeln!(file, "guide h {}", y)?,
+                Guide::Vert(x) => writeln!(file, "guide v {}", x)?,
+            }
+        }
+        for info in self.layers.iter() {
+            let mut flags = String::new();
+            if info.visible {
+                flags.push('v');
+            }
+            if info.locked {
+                flags.push('l');
+            }
+            if info.active {
+                flags.push('a');
+            }
+            if flags.is_empty() {
+                flags.push('-');
+            }
+            writeln!(file, "layer {} {}", info.image_index, flags)?;
+        }
+        Ok(())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ramps.is_empty()
+            && self.grid.is_none()
+            && self.guides.is_empty()
+            && self.layers.is_empty()
+    }
+}
+
+//===========================================================================//
+
+pub fn load_layer_images(document_path: &str) -> io::Result<Vec<Image>> {
+    match util::load_ahi_from_file(&layers_path(document_path)) {
+        Ok(collection) => Ok(collection.images),
+        Err(ref error) if error.kind() == io::ErrorKind::NotFound => {
+            Ok(Vec::new())
+        }
+        Err(error) => Err(error),
+    }
+}
+
+pub fn save_layer_images(
+    document_path: &str,
+    images: Vec<Image>,
+) -> io::Result<()> {
+    let path = layers_path(document_path);
+    if images.is_empty() {
+        return match fs::remove_file(&path) {
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => {
+                Ok(())
+            }
+            result => result,
+        };
+    }
+    let collection = Collection { images, palettes: Vec::new() };
+    collection.write(&mut File::create(&path)?)
+}
+
+//===========================================================================//
+
+pub fn format_ramp(ramp: &[Color]) -> String {
+    ramp.iter()
+        .map(|&color| format!("{:X}", util::color_index(color)))
+        .collect()
+}
+
+pub fn parse_ramp(text: &str) -> Option<Vec<Color>> {
+    let ramp: Vec<Color> = text
+        .chars()
+        .map(|chr| chr.to_digit(16).map(|d| util::color_at_index(d as usize)))
+        .collect::<Option<_>>()?;
+    if ramp.len() >= 2 {
+        Some(ramp)
+    } else {
+        None
+    }
+}
+
+fn parse_guide(kind: Option<&str>, position: Option<&str>) -> Option<Guide> {
+    let position = position?.parse::<u32>().ok()?;
+    match kind? {
+        "h" => Some(Guide::Horz(position)),
+        "v" => Some(Guide::Vert(position)),
+        _ => None,
+    }
+}
+
+fn parse_layer_info(
+    index: Option<&str>,
+    flags: Option<&str>,
+) -> Option<LayerInfo> {
+    let image_index = index?.parse::<usize>().ok()?;
+    let flags = flags?;
+    if !flags.chars().all(|chr| "vla-".contains(chr)) {
+        return None;
+    }
+    Some(LayerInfo {
+        image_index,
+        visible: flags.contains('v'),
+        locked: flags.contains('l'),
+        active: flags.contains('a'),
+    })
+}
+
+fn sidecar_path(document_path: &str) -> String {
+    format!("{}.tuna", document_path)
+}
+
+fn layers_path(document_path: &str) -> String {
+    format!("{}.layers.ahi", document_path)
+}
+
+//===========================================================================//
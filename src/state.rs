@@ -18,30 +18,46 @@
 // +--------------------------------------------------------------------------+
 
 use ahi::{Collection, Color, Font, Glyph, Image, Palette};
+use num_integer::mod_floor;
 use sdl2::rect::{Point, Rect};
+use std::borrow::Cow;
+use std::cmp;
 use std::fs::File;
 use std::io;
 use std::mem;
 use std::ops::Deref;
 use std::rc::Rc;
+use crate::dither::Dither;
+use crate::grid::{Grid, Guide};
+use crate::layer::{Layer, LayerStack};
+use crate::reference::Reference;
+use crate::sidecar::{self, LayerInfo, Sidecar};
+use crate::transform::{self, Anchor, Resample, Shear, Upscale};
 use crate::util;
 
 //===========================================================================//
 
 #[derive(Clone, Copy, Eq, PartialEq)]
 pub enum Tool {
-    Checkerboard,
+    Curve,
+    DitherBrush,
+    DitherFill,
     Eyedropper,
+    FilledOval,
+    FilledRectangle,
+    Gradient,
     Lasso,
     Line,
+    Measure,
     Oval,
     PaintBucket,
     PaletteReplace,
     PaletteSwap,
     Pencil,
+    Polygon,
     Rectangle,
     Select,
-    Watercolor,
+    Shade,
 }
 
 #[derive(Clone, Copy, Eq, PartialEq)]
@@ -52,6 +68,9 @@ pub enum Mirror {
     Both,
     Rot2,
     Rot4,
+    Diag,
+    AntiDiag,
+    Kaleido,
 }
 
 //===========================================================================//
@@ -62,6 +81,7 @@ struct AhiData {
     palettes: Vec<Rc<Palette>>,
     image_index: usize,
     images: Vec<Rc<Image>>,
+    layers: Vec<Option<LayerStack>>,
 }
 
 impl AhiData {
@@ -73,9 +93,38 @@ impl AhiData {
             palette_index: 0,
             palettes: collection.palettes.drain(..).map(Rc::new).collect(),
             image_index: 0,
+            layers: vec![None; collection.images.len()],
             images: collection.images.drain(..).map(Rc::new).collect(),
         }
     }
+
+    fn composite(&self, index: usize) -> Cow<'_, Image> {
+        match self.layers[index] {
+            Some(ref stack) => {
+                Cow::Owned(stack.composite(&self.images[index]))
+            }
+            None => Cow::Borrowed(&self.images[index]),
+        }
+    }
+
+    /// Returns the images of all layers of the image, whether visible or not.
+    fn layer_images(&self, index: usize) -> Vec<Rc<Image>> {
+        match self.layers[index] {
+            Some(ref stack) => stack
+                .layers(&self.images[index])
+                .into_iter()
+                .map(|layer| layer.image)
+                .collect(),
+            None => vec![self.images[index].clone()],
+        }
+    }
+
+    fn map_image(&mut self, index: usize, func: &dyn Fn(&Image) -> Image) {
+        self.images[index] = Rc::new(func(&self.images[index]));
+        if let Some(ref mut stack) = self.layers[index] {
+            stack.map_inactive(func);
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -100,6 +149,7 @@ impl Data {
 struct Snapshot {
     data: Data,
     selection: Option<(Rc<Image>, Point)>,
+    ramps: Vec<Vec<Color>>,
     unsaved: bool,
 }
 
@@ -107,39 +157,70 @@ struct Snapshot {
 
 pub struct EditorState {
     color: Color,
+    secondary_color: Option<Color>,
     filepath: String,
     current: Snapshot,
     undo_stack: Vec<Snapshot>,
     redo_stack: Vec<Snapshot>,
     clipboard: Option<(Rc<Image>, Point)>,
+    brush: Option<Rc<Image>>,
+    pixel_perfect: bool,
+    seamless: bool,
+    checkerboard: bool,
+    index_view: bool,
+    onion_skin: u32,
+    preview_palette: Option<Palette>,
+    reference: Option<Reference>,
+    dither: Dither,
     tool: Tool,
     prev_tool: Tool,
     mirror: Mirror,
+    mirror_axes: Option<((i32, i32), (u32, u32))>,
     persistent_mutation_active: bool,
-    grid: (u32, u32),
+    grid: Grid,
+    guides: Vec<Guide>,
+    // The layer settings from the sidecar file, if the layers failed to
+    // load; while set, saving leaves the document's layer file alone.
+    unloaded_layers: Option<Vec<LayerInfo>>,
     test_sentence: String,
 }
 
 impl EditorState {
     pub fn new(filepath: String, collection: Collection) -> EditorState {
-        EditorState {
+        let mut state = EditorState {
             color: Color::C1,
+            secondary_color: None,
             filepath,
             current: Snapshot {
                 data: Data::from_collection(collection),
                 selection: None,
+                ramps: Vec::new(),
                 unsaved: false,
             },
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
             clipboard: None,
+            brush: None,
+            pixel_perfect: false,
+            seamless: false,
+            checkerboard: true,
+            index_view: false,
+            onion_skin: 0,
+            preview_palette: None,
+            reference: None,
+            dither: Dither::default(),
             tool: Tool::Pencil,
             prev_tool: Tool::Pencil,
             mirror: Mirror::None,
+            mirror_axes: None,
             persistent_mutation_active: false,
-            grid: (0, 0),
+            grid: Grid::default(),
+            guides: Vec::new(),
+            unloaded_layers: None,
             test_sentence: DEFAULT_TEST_SENTENCE.to_string(),
-        }
+        };
+        state.load_sidecar();
+        state
     }
 
     pub fn is_unsaved(&self) -> bool {
@@ -162,12 +243,124 @@ impl EditorState {
         self.color = color;
     }
 
-    pub fn grid(&self) -> (u32, u32) {
-        self.grid
+    pub fn secondary_color(&self) -> Option<Color> {
+        self.secondary_color
+    }
+
+    pub fn set_secondary_color(&mut self, color: Option<Color>) {
+        self.secondary_color = color;
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn set_grid(&mut self, grid: Grid) {
+        if self.grid != grid {
+            self.grid = grid;
+            self.current.unsaved = true;
+        }
+    }
+
+    pub fn guides(&self) -> &[Guide] {
+        &self.guides
+    }
+
+    pub fn set_guides(&mut self, guides: Vec<Guide>) {
+        if self.guides != guides {
+            self.guides = guides;
+            self.current.unsaved = true;
+        }
+    }
+
+    pub fn brush(&self) -> Option<&Image> {
+        self.brush.as_deref()
+    }
+
+    pub fn capture_brush(&mut self) -> bool {
+        let brush = match self.current.selection {
+            Some((ref image, _)) => Some(image.clone()),
+            None => self.clipboard.as_ref().map(|(image, _)| image.clone()),
+        };
+        if brush.is_some() {
+            self.brush = brush;
+            self.set_tool(Tool::Pencil);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn clear_brush(&mut self) -> bool {
+        self.brush.take().is_some()
+    }
+
+    pub fn pixel_perfect(&self) -> bool {
+        self.pixel_perfect
+    }
+
+    pub fn set_pixel_perfect(&mut self, pixel_perfect: bool) {
+        self.pixel_perfect = pixel_perfect;
+    }
+
+    pub fn seamless(&self) -> bool {
+        self.seamless
     }
 
-    pub fn set_grid(&mut self, horz: u32, vert: u32) {
-        self.grid = (horz, vert);
+    pub fn set_seamless(&mut self, seamless: bool) {
+        self.seamless = seamless;
+    }
+
+    pub fn checkerboard(&self) -> bool {
+        self.checkerboard
+    }
+
+    pub fn set_checkerboard(&mut self, checkerboard: bool) {
+        self.checkerboard = checkerboard;
+    }
+
+    pub fn index_view(&self) -> bool {
+        self.index_view
+    }
+
+    pub fn set_index_view(&mut self, index_view: bool) {
+        self.index_view = index_view;
+    }
+
+    pub fn onion_skin(&self) -> u32 {
+        self.onion_skin
+    }
+
+    pub fn set_onion_skin(&mut self, frames: u32) {
+        self.onion_skin = frames;
+    }
+
+    pub fn reference(&self) -> Option<&Reference> {
+        self.reference.as_ref()
+    }
+
+    pub fn set_reference(&mut self, reference: Option<Reference>) {
+        self.reference = reference;
+    }
+
+    pub fn dither(&self) -> Dither {
+        self.dither
+    }
+
+    pub fn set_dither(&mut self, dither: Dither) {
+        self.dither = dither;
+    }
+
+    pub fn ramps(&self) -> &[Vec<Color>] {
+        &self.current.ramps
+    }
+
+    pub fn ramp_containing(&self, color: Color) -> Option<&[Color]> {
+        self.current
+            .ramps
+            .iter()
+            .find(|ramp| ramp.contains(&color))
+            .map(|ramp| ramp.as_slice())
     }
 
     pub fn tool(&self) -> Tool {
@@ -176,7 +369,11 @@ impl EditorState {
 
     pub fn set_tool(&mut self, tool: Tool) {
         if self.tool != tool {
-            self.unselect_if_necessary();
+            if tool == Tool::Gradient {
+                self.reset_persistent_mutation();
+            } else {
+                self.unselect_if_necessary();
+            }
             self.prev_tool = self.tool;
             self.tool = tool;
         }
@@ -190,59 +387,58 @@ impl EditorState {
         self.mirror = mirror;
     }
 
+    pub fn mirror_axes(&self) -> (i32, i32) {
+        // Custom axes only apply to images of the size they were placed for.
+        let (width, height) = self.image_size();
+        match self.mirror_axes {
+            Some((axes, size)) if size == (width, height) => axes,
+            _ => (width as i32 - 1, height as i32 - 1),
+        }
+    }
+
+    pub fn set_mirror_axes(&mut self, axes: Option<(i32, i32)>) {
+        let size = self.image_size();
+        self.mirror_axes = axes.map(|axes| (axes, size));
+    }
+
     pub fn mirror_positions(&self, (x, y): (u32, u32)) -> Vec<(u32, u32)> {
         let (width, height) = self.image_size();
         debug_assert!(x < width);
         debug_assert!(y < height);
-        let mirror = self.mirror();
+        // The axes are stored doubled, so that they can fall either on a
+        // pixel or between two pixels.
+        let (axis_x, axis_y) = self.mirror_axes();
+        let shift = (axis_x - axis_y) / 2;
+        let (x, y) = (x as i32, y as i32);
+        let horz = (axis_x - x, y);
+        let vert = (x, axis_y - y);
+        let rot2 = (axis_x - x, axis_y - y);
+        let rot_cw = (axis_y - y + shift, x - shift);
+        let rot_ccw = (y + shift, axis_x - x - shift);
+        let diag = (y + shift, x - shift);
+        let anti_diag = (axis_y - y + shift, axis_x - x - shift);
         let mut positions = vec![(x, y)];
-        if mirror == Mirror::Horz || mirror == Mirror::Both {
-            positions.push((width - x - 1, y));
-        }
-        if mirror == Mirror::Vert || mirror == Mirror::Both {
-            positions.push((x, height - y - 1));
-        }
-        if mirror == Mirror::Both
-            || mirror == Mirror::Rot2
-            || mirror == Mirror::Rot4
-        {
-            positions.push((width - x - 1, height - y - 1));
-        }
-        if mirror == Mirror::Rot4 {
-            let mut x1 = (height - y - 1) as i32;
-            let mut y1 = x as i32;
-            let mut x2 = y as i32;
-            let mut y2 = (width - x - 1) as i32;
-            if width > height {
-                let diff = ((width - height) / 2) as i32;
-                x1 += diff;
-                x2 += diff;
-                y1 -= diff;
-                y2 -= diff;
-            }
-            if height > width {
-                let diff = ((height - width) / 2) as i32;
-                x1 -= diff;
-                x2 -= diff;
-                y1 += diff;
-                y2 += diff;
-            }
-            if x1 >= 0
-                && (x1 as u32) < width
-                && y1 >= 0
-                && (y1 as u32) < height
-            {
-                positions.push((x1 as u32, y1 as u32));
-            }
-            if x2 >= 0
-                && (x2 as u32) < width
-                && y2 >= 0
-                && (y2 as u32) < height
-            {
-                positions.push((x2 as u32, y2 as u32));
+        match self.mirror() {
+            Mirror::None => {}
+            Mirror::Horz => positions.push(horz),
+            Mirror::Vert => positions.push(vert),
+            Mirror::Both => positions.extend(&[horz, vert, rot2]),
+            Mirror::Rot2 => positions.push(rot2),
+            Mirror::Rot4 => positions.extend(&[rot2, rot_cw, rot_ccw]),
+            Mirror::Diag => positions.push(diag),
+            Mirror::AntiDiag => positions.push(anti_diag),
+            Mirror::Kaleido => {
+                positions.extend(&[horz, vert, rot2, rot_cw, rot_ccw]);
+                positions.extend(&[diag, anti_diag]);
             }
         }
         positions
+            .into_iter()
+            .filter(|&(x, y)| {
+                x >= 0 && (x as u32) < width && y >= 0 && (y as u32) < height
+            })
+            .map(|(x, y)| (x as u32, y as u32))
+            .collect()
     }
 
     pub fn test_sentence(&self) -> &String {
@@ -288,6 +484,24 @@ impl EditorState {
     }
 
     pub fn palette(&self) -> &Palette {
+        match self.preview_palette {
+            Some(ref palette) => palette,
+            None => self.document_palette(),
+        }
+    }
+
+    pub fn set_color_preview(
+        &mut self,
+        preview: Option<(Color, (u8, u8, u8, u8))>,
+    ) {
+        self.preview_palette = preview.map(|(color, rgba)| {
+            let mut palette = Palette::clone(self.document_palette());
+            palette[color] = rgba;
+            palette
+        });
+    }
+
+    fn document_palette(&self) -> &Palette {
         match self.current.data {
             Data::AHI(ref ahi) => {
                 if ahi.palette_index < ahi.palettes.len() {
@@ -418,6 +632,37 @@ impl EditorState {
         }
     }
 
+    pub fn composite_image(&self) -> Cow<'_, Image> {
+        match self.current.data {
+            Data::AHI(ref ahi) => ahi.composite(ahi.image_index),
+            Data::AHF(_) => Cow::Borrowed(self.image()),
+        }
+    }
+
+    pub fn composite_image_at(&self, index: usize) -> Cow<'_, Image> {
+        match self.current.data {
+            Data::AHI(ref ahi) => ahi.composite(index),
+            Data::AHF(_) => Cow::Borrowed(self.image_at(index)),
+        }
+    }
+
+    pub fn layers(&self) -> Option<&LayerStack> {
+        match self.current.data {
+            Data::AHI(ref ahi) => ahi.layers[ahi.image_index].as_ref(),
+            Data::AHF(_) => None,
+        }
+    }
+
+    pub fn active_layer_editable(&self) -> bool {
+        match self.layers() {
+            Some(stack) => {
+                let active = stack.active();
+                stack.is_visible(active) && !stack.is_locked(active)
+            }
+            None => true,
+        }
+    }
+
     pub fn image_at(&self, index: usize) -> &Image {
         match self.current.data {
             Data::AHI(ref ahi) => &ahi.images[index],
@@ -518,8 +763,9 @@ impl EditorState {
         let mut file = File::create(&self.filepath)?;
         match self.current.data {
             Data::AHI(ref ahi) => {
-                let images: Vec<Image> =
-                    ahi.images.iter().map(|rc| rc.deref().clone()).collect();
+                let images: Vec<Image> = (0..ahi.images.len())
+                    .map(|index| ahi.composite(index).into_owned())
+                    .collect();
                 let palettes: Vec<Palette> =
                     ahi.palettes.iter().map(|rc| rc.deref().clone()).collect();
                 let collection = Collection { images, palettes };
@@ -529,6 +775,40 @@ impl EditorState {
                 ahf.font.write(file)?;
             }
         }
+        let mut layer_infos = Vec::new();
+        let mut layer_images = Vec::new();
+        if let Some(ref infos) = self.unloaded_layers {
+            println!("Not saving layers, since they failed to load");
+            layer_infos = infos.clone();
+        } else if let Data::AHI(ref ahi) = self.current.data {
+            for (index, stack) in ahi.layers.iter().enumerate() {
+                if let Some(ref stack) = *stack {
+                    let layers = stack.layers(&ahi.images[index]);
+                    for (layer_index, layer) in layers.into_iter().enumerate()
+                    {
+                        layer_infos.push(LayerInfo {
+                            image_index: index,
+                            visible: layer.visible,
+                            locked: layer.locked,
+                            active: layer_index == stack.active(),
+                        });
+                        layer_images.push(layer.image.deref().clone());
+                    }
+                }
+            }
+        }
+        let grid =
+            if self.grid != Grid::default() { Some(self.grid) } else { None };
+        let sidecar = Sidecar {
+            ramps: self.current.ramps.clone(),
+            grid,
+            guides: self.guides.clone(),
+            layers: layer_infos,
+        };
+        sidecar.save(&self.filepath)?;
+        if self.unloaded_layers.is_none() {
+            sidecar::save_layer_images(&self.filepath, layer_images)?;
+        }
         self.current.unsaved = false;
         for snapshot in self.undo_stack.iter_mut() {
             snapshot.unsaved = true;
@@ -549,10 +829,83 @@ impl EditorState {
 
     fn load_data(&mut self, path: String, data: Data) {
         self.filepath = path;
-        self.current = Snapshot { data, selection: None, unsaved: false };
+        self.current = Snapshot {
+            data,
+            selection: None,
+            ramps: Vec::new(),
+            unsaved: false,
+        };
         self.undo_stack.clear();
         self.redo_stack.clear();
         self.persistent_mutation_active = false;
+        self.mirror_axes = None;
+        self.load_sidecar();
+    }
+
+    fn load_sidecar(&mut self) {
+        match Sidecar::load(&self.filepath) {
+            Ok(sidecar) => {
+                self.current.ramps = sidecar.ramps;
+                self.grid = sidecar.grid.unwrap_or_default();
+                self.guides = sidecar.guides;
+                self.unloaded_layers = None;
+                if let Err(error) = self.load_layers(&sidecar.layers) {
+                    println!("Error loading layers: {}", error);
+                    self.unloaded_layers = Some(sidecar.layers);
+                }
+            }
+            Err(error) => {
+                println!("Error loading settings: {}", error);
+                self.current.ramps = Vec::new();
+                self.grid = Grid::default();
+                self.guides = Vec::new();
+                self.unloaded_layers = None;
+            }
+        }
+    }
+
+    fn load_layers(&mut self, infos: &[LayerInfo]) -> io::Result<()> {
+        if infos.is_empty() {
+            return Ok(());
+        }
+        let images = sidecar::load_layer_images(&self.filepath)?;
+        let ahi = match self.current.data {
+            Data::AHI(ref mut ahi) => ahi,
+            Data::AHF(_) => return Ok(()),
+        };
+        let num_images = ahi.images.len();
+        if images.len() != infos.len()
+            || infos.iter().any(|info| info.image_index >= num_images)
+        {
+            let msg = "layer data does not match the document";
+            return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+        }
+        let mut groups: Vec<Vec<Layer>> = vec![Vec::new(); num_images];
+        let mut actives: Vec<usize> = vec![0; num_images];
+        for (info, image) in infos.iter().zip(images) {
+            let group = &mut groups[info.image_index];
+            if info.active {
+                actives[info.image_index] = group.len();
+            }
+            group.push(Layer {
+                image: Rc::new(image),
+                visible: info.visible,
+                locked: info.locked,
+            });
+        }
+        for (index, layers) in groups.into_iter().enumerate() {
+            if layers.is_empty() {
+                continue;
+            }
+            let stack = LayerStack::new(layers, actives[index]);
+            if !stack.matches(&ahi.images[index]) {
+                println!("Layers of image {} are out of date", index);
+                continue;
+            }
+            ahi.images[index] = stack.live_image(&ahi.images[index]);
+            ahi.layers[index] = Some(stack);
+        }
+        Ok(())
     }
 }
 
@@ -585,8 +938,19 @@ impl<'a> Mutation<'a> {
         }
     }
 
+    pub fn selected_image(&mut self) -> Option<&mut Image> {
+        match self.state.current.selection {
+            Some((ref mut image, _)) => Some(Rc::make_mut(image)),
+            None => None,
+        }
+    }
+
     pub fn color_pixel(&mut self, position: (u32, u32)) {
         let color = self.state.color();
+        self.color_pixel_with(position, color);
+    }
+
+    pub fn color_pixel_with(&mut self, position: (u32, u32), color: Color) {
         let positions = self.state.mirror_positions(position);
         let image = self.image();
         for pos in positions {
@@ -594,6 +958,39 @@ impl<'a> Mutation<'a> {
         }
     }
 
+    pub fn stamp_brush(&mut self, position: (u32, u32)) {
+        let brush = match self.state.brush {
+            Some(ref brush) => brush.clone(),
+            None => {
+                self.color_pixel(position);
+                return;
+            }
+        };
+        let (width, height) = self.state.image_size();
+        let seamless = self.state.seamless;
+        let left = (position.0 as i32) - (brush.width() / 2) as i32;
+        let top = (position.1 as i32) - (brush.height() / 2) as i32;
+        for row in 0..brush.height() {
+            for col in 0..brush.width() {
+                let color = brush[(col, row)];
+                let mut x = left + col as i32;
+                let mut y = top + row as i32;
+                if seamless {
+                    x = mod_floor(x, width as i32);
+                    y = mod_floor(y, height as i32);
+                }
+                if color != Color::C0
+                    && x >= 0
+                    && (x as u32) < width
+                    && y >= 0
+                    && (y as u32) < height
+                {
+                    self.color_pixel_with((x as u32, y as u32), color);
+                }
+            }
+        }
+    }
+
     pub fn add_new_palette(&mut self) -> bool {
         self.unselect();
         let new_palette = self.state.palette().clone();
@@ -654,6 +1051,48 @@ impl<'a> Mutation<'a> {
         }
     }
 
+    pub fn generate_ramp(
+        &mut self,
+        start: Color,
+        end: Color,
+        hue_shift: f64,
+    ) -> bool {
+        self.unselect();
+        let start_index = util::color_index(start);
+        let end_index = util::color_index(end);
+        let (low, high) = if start_index < end_index {
+            (start_index, end_index)
+        } else {
+            (end_index, start_index)
+        };
+        if high - low < 2 {
+            return false;
+        }
+        match self.state.current.data {
+            Data::AHI(ref mut ahi) => {
+                if ahi.palette_index < ahi.palettes.len() {
+                    let mut palette =
+                        Palette::clone(&ahi.palettes[ahi.palette_index]);
+                    let start_rgba = palette[start];
+                    let end_rgba = palette[end];
+                    for index in (low + 1)..high {
+                        let t = ((index as f64) - (start_index as f64))
+                            / ((end_index as f64) - (start_index as f64));
+                        palette[util::color_at_index(index)] =
+                            util::interpolate_color(
+                                start_rgba, end_rgba, t, hue_shift,
+                            );
+                    }
+                    ahi.palettes[ahi.palette_index] = Rc::new(palette);
+                    true
+                } else {
+                    false
+                }
+            }
+            Data::AHF(_) => false,
+        }
+    }
+
     pub fn add_new_image(&mut self, chr: char) -> bool {
         self.unselect();
         let (width, height) = self.state.image_size();
@@ -662,6 +1101,7 @@ impl<'a> Mutation<'a> {
                 ahi.image_index += 1;
                 let rc = Rc::new(Image::new(width, height));
                 ahi.images.insert(ahi.image_index, rc);
+                ahi.layers.insert(ahi.image_index, None);
                 true
             }
             Data::AHF(ref mut ahf) => {
@@ -698,6 +1138,7 @@ impl<'a> Mutation<'a> {
                 if ahi.images.len() > 1 {
                     let index = ahi.image_index;
                     ahi.images.remove(index);
+                    ahi.layers.remove(index);
                     if index == ahi.images.len() {
                         ahi.image_index -= 1;
                     }
@@ -718,25 +1159,46 @@ impl<'a> Mutation<'a> {
         }
     }
 
-    pub fn resize_images(&mut self, new_width: u32, new_height: u32) {
+    pub fn resize_images(
+        &mut self,
+        new_width: u32,
+        new_height: u32,
+        anchor: Anchor,
+        fill: Color,
+        all: bool,
+    ) {
         self.unselect();
+        let resize = |image: &Image, width: u32, height: u32| {
+            transform::resize(image, width, height, anchor, fill)
+        };
         match self.state.current.data {
             Data::AHI(ref mut ahi) => {
-                ahi.images = ahi
-                    .images
-                    .iter()
-                    .map(|image| Rc::new(image.crop(new_width, new_height)))
-                    .collect();
+                let indices: Vec<usize> = if all {
+                    (0..ahi.images.len()).collect()
+                } else {
+                    vec![ahi.image_index]
+                };
+                for index in indices {
+                    ahi.map_image(index, &|image| {
+                        resize(image, new_width, new_height)
+                    });
+                }
             }
             Data::AHF(ref mut ahf) => {
-                if new_height != ahf.font.glyph_height() {
+                let old_height = ahf.font.glyph_height();
+                if new_height != old_height {
+                    let (_, top) =
+                        anchor.offset((0, old_height), (0, new_height));
                     let mut font = Font::with_glyph_height(new_height);
+                    font.set_baseline(ahf.font.baseline() + top);
                     {
                         let glyph = ahf.font.default_glyph();
                         let new_glyph = Glyph::new(
-                            glyph
-                                .image()
-                                .crop(glyph.image().width(), new_height),
+                            resize(
+                                glyph.image(),
+                                glyph.image().width(),
+                                new_height,
+                            ),
                             glyph.left_edge(),
                             glyph.right_edge(),
                         );
@@ -745,9 +1207,11 @@ impl<'a> Mutation<'a> {
                     for chr in ahf.font.chars() {
                         let glyph = &ahf.font[chr];
                         let new_glyph = Glyph::new(
-                            glyph
-                                .image()
-                                .crop(glyph.image().width(), new_height),
+                            resize(
+                                glyph.image(),
+                                glyph.image().width(),
+                                new_height,
+                            ),
                             glyph.left_edge(),
                             glyph.right_edge(),
                         );
@@ -755,36 +1219,193 @@ impl<'a> Mutation<'a> {
                     }
                     ahf.font = font;
                 }
+                let resize_width = |glyph: &Glyph| {
+                    let old_width = glyph.image().width();
+                    let (left, _) =
+                        anchor.offset((old_width, 0), (new_width, 0));
+                    Glyph::new(
+                        resize(
+                            glyph.image(),
+                            new_width,
+                            glyph.image().height(),
+                        ),
+                        glyph.left_edge() + left,
+                        glyph.right_edge() + left,
+                    )
+                };
                 match ahf.current_char {
                     Some(chr) => {
-                        let new_glyph = {
-                            let glyph = &ahf.font[chr];
-                            Glyph::new(
-                                glyph
-                                    .image()
-                                    .crop(new_width, glyph.image().height()),
-                                glyph.left_edge(),
-                                glyph.right_edge(),
-                            )
-                        };
+                        let new_glyph = resize_width(&ahf.font[chr]);
                         ahf.font.set_char_glyph(chr, new_glyph);
                     }
                     None => {
-                        let new_glyph = {
-                            let glyph = ahf.font.default_glyph();
-                            Glyph::new(
-                                glyph
-                                    .image()
-                                    .crop(new_width, glyph.image().height()),
-                                glyph.left_edge(),
-                                glyph.right_edge(),
-                            )
+                        let new_glyph = resize_width(ahf.font.default_glyph());
+                        ahf.font.set_default_glyph(new_glyph);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn offset_images(&mut self, dx: i32, dy: i32, all: bool) {
+        self.unselect();
+        match self.state.current.data {
+            Data::AHI(ref mut ahi) => {
+                let indices: Vec<usize> = if all {
+                    (0..ahi.images.len()).collect()
+                } else {
+                    vec![ahi.image_index]
+                };
+                for index in indices {
+                    ahi.map_image(index, &|image| {
+                        transform::wrap_shift(image, dx, dy)
+                    });
+                }
+            }
+            Data::AHF(_) if !all => {
+                let shifted = transform::wrap_shift(self.image(), dx, dy);
+                *self.image() = shifted;
+            }
+            Data::AHF(ref mut ahf) => {
+                let shift = |glyph: &Glyph| {
+                    Glyph::new(
+                        transform::wrap_shift(glyph.image(), dx, dy),
+                        glyph.left_edge(),
+                        glyph.right_edge(),
+                    )
+                };
+                let default_glyph = shift(ahf.font.default_glyph());
+                ahf.font.set_default_glyph(default_glyph);
+                let chars: Vec<char> = ahf.font.chars().collect();
+                for chr in chars {
+                    let new_glyph = shift(&ahf.font[chr]);
+                    ahf.font.set_char_glyph(chr, new_glyph);
+                }
+            }
+        }
+    }
+
+    /// Trims the current image (or all images, to a shared bounding box) to
+    /// its non-transparent pixels.  If `offset_slots` is given, the left and
+    /// top offsets that were trimmed off are added to those two metadata
+    /// values of each image, so that sprites can stay aligned.
+    pub fn trim_images(
+        &mut self,
+        all: bool,
+        offset_slots: Option<(usize, usize)>,
+    ) -> bool {
+        self.unselect();
+        let mut changed = false;
+        match self.state.current.data {
+            Data::AHI(ref mut ahi) => {
+                let indices: Vec<usize> = if all {
+                    (0..ahi.images.len()).collect()
+                } else {
+                    vec![ahi.image_index]
+                };
+                let mut bounds: Option<(i32, i32, i32, i32)> = None;
+                for &index in indices.iter() {
+                    for image in ahi.layer_images(index) {
+                        let image_bounds = transform::opaque_bounds(&image);
+                        bounds = match (bounds, image_bounds) {
+                            (
+                                Some((x0, y0, x1, y1)),
+                                Some((x2, y2, x3, y3)),
+                            ) => Some((
+                                cmp::min(x0, x2),
+                                cmp::min(y0, y2),
+                                cmp::max(x1, x3),
+                                cmp::max(y1, y3),
+                            )),
+                            (bounds, None) | (None, bounds) => bounds,
                         };
+                    }
+                }
+                let (min_x, min_y, max_x, max_y) = match bounds {
+                    Some(bounds) => bounds,
+                    None => return false,
+                };
+                for index in indices {
+                    // Clamp the shared box to each image, so that smaller
+                    // images are never padded out to a larger size.
+                    let image = &ahi.images[index];
+                    let right = cmp::min(max_x + 1, image.width() as i32);
+                    let bottom = cmp::min(max_y + 1, image.height() as i32);
+                    if right <= min_x || bottom <= min_y {
+                        continue;
+                    }
+                    let width = (right - min_x) as u32;
+                    let height = (bottom - min_y) as u32;
+                    if (min_x, min_y) == (0, 0)
+                        && image.width() == width
+                        && image.height() == height
+                    {
+                        continue;
+                    }
+                    ahi.map_image(index, &|image| {
+                        transform::crop_rect(
+                            image, min_x, min_y, width, height,
+                        )
+                    });
+                    if let Some((x_slot, y_slot)) = offset_slots {
+                        let trimmed = Rc::make_mut(&mut ahi.images[index]);
+                        let mut metadata = trimmed.metadata().clone();
+                        let len = cmp::max(x_slot, y_slot) + 1;
+                        if metadata.len() < len {
+                            metadata.resize(len, 0);
+                        }
+                        let add = |value: i16, offset: i32| {
+                            let offset = cmp::min(offset, i16::MAX as i32);
+                            value.saturating_add(offset as i16)
+                        };
+                        metadata[x_slot] = add(metadata[x_slot], min_x);
+                        metadata[y_slot] = add(metadata[y_slot], min_y);
+                        trimmed.set_metadata(metadata);
+                    }
+                    changed = true;
+                }
+            }
+            Data::AHF(ref mut ahf) => {
+                let trim = |glyph: &Glyph| {
+                    let image = glyph.image();
+                    let (min_x, _, max_x, _) =
+                        transform::opaque_bounds(image)?;
+                    let width = (max_x - min_x + 1) as u32;
+                    if width == image.width() {
+                        return None;
+                    }
+                    Some(Glyph::new(
+                        transform::crop_rect(
+                            image,
+                            min_x,
+                            0,
+                            width,
+                            image.height(),
+                        ),
+                        glyph.left_edge() - min_x,
+                        glyph.right_edge() - min_x,
+                    ))
+                };
+                let chars: Vec<char> = if all {
+                    ahf.font.chars().collect()
+                } else {
+                    ahf.current_char.into_iter().collect()
+                };
+                if all || ahf.current_char.is_none() {
+                    if let Some(new_glyph) = trim(ahf.font.default_glyph()) {
                         ahf.font.set_default_glyph(new_glyph);
+                        changed = true;
+                    }
+                }
+                for chr in chars {
+                    if let Some(new_glyph) = trim(&ahf.font[chr]) {
+                        ahf.font.set_char_glyph(chr, new_glyph);
+                        changed = true;
                     }
                 }
             }
         }
+        changed
     }
 
     pub fn set_metadata(&mut self, data: Vec<i16>) {
@@ -810,6 +1431,10 @@ impl<'a> Mutation<'a> {
         }
     }
 
+    pub fn set_ramps(&mut self, ramps: Vec<Vec<Color>>) {
+        self.state.current.ramps = ramps;
+    }
+
     pub fn set_tag(&mut self, tag: String) {
         if let Data::AHI(ref mut ahi) = self.state.current.data {
             Rc::make_mut(&mut ahi.images[ahi.image_index]).set_tag(tag);
@@ -858,6 +1483,94 @@ impl<'a> Mutation<'a> {
         self.select(&Rect::new(0, 0, width, height));
     }
 
+    fn layer_stack(&mut self) -> Option<(&mut LayerStack, &mut Rc<Image>)> {
+        match self.state.current.data {
+            Data::AHI(ref mut ahi) => {
+                let index = ahi.image_index;
+                let live = &mut ahi.images[index];
+                let stack = ahi.layers[index].get_or_insert_with(|| {
+                    LayerStack::new(vec![Layer::new(live.clone())], 0)
+                });
+                Some((stack, live))
+            }
+            Data::AHF(_) => None,
+        }
+    }
+
+    pub fn add_layer(&mut self) -> bool {
+        self.unselect();
+        let (width, height) = self.state.image_size();
+        if let Some((stack, live)) = self.layer_stack() {
+            let layer = Layer::new(Rc::new(Image::new(width, height)));
+            let index = stack.active() + 1;
+            stack.insert(index, layer, live);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn delete_layer(&mut self) -> bool {
+        self.unselect();
+        match self.layer_stack() {
+            Some((stack, live)) if stack.len() > 1 => {
+                stack.remove_active(live);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn move_layer(&mut self, up: bool) -> bool {
+        self.unselect();
+        if let Some((stack, _)) = self.layer_stack() {
+            let active = stack.active();
+            if up && active + 1 < stack.len() {
+                stack.move_active(active + 1);
+                return true;
+            } else if !up && active > 0 {
+                stack.move_active(active - 1);
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn select_layer(&mut self, index: usize) -> bool {
+        self.unselect();
+        match self.layer_stack() {
+            Some((stack, live)) if index < stack.len() => {
+                stack.set_active(index, live);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn toggle_layer_visible(&mut self, index: usize) -> bool {
+        self.unselect();
+        match self.layer_stack() {
+            Some((stack, _)) if index < stack.len() => {
+                let visible = stack.is_visible(index);
+                stack.set_visible(index, !visible);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn toggle_layer_locked(&mut self, index: usize) -> bool {
+        self.unselect();
+        match self.layer_stack() {
+            Some((stack, _)) if index < stack.len() => {
+                let locked = stack.is_locked(index);
+                stack.set_locked(index, !locked);
+                true
+            }
+            _ => false,
+        }
+    }
+
     pub fn unselect(&mut self) {
         if let Some((image, position)) = self.state.current.selection.take() {
             self.image().draw(&image, position.x(), position.y());
@@ -900,11 +1613,88 @@ impl<'a> Mutation<'a> {
         }
     }
 
-    pub fn scale_selection_up(&mut self, by: u32) {
+    pub fn rotate_selection_by(&mut self, degrees: f64) {
+        if let Some((ref mut image, ref mut position)) =
+            self.state.current.selection
+        {
+            let rotated = transform::rotsprite(image, degrees);
+            *position = position.offset(
+                (image.width() as i32 - rotated.width() as i32) / 2,
+                (image.height() as i32 - rotated.height() as i32) / 2,
+            );
+            *image = Rc::new(rotated);
+        } else {
+            let rotated = transform::rotsprite(self.image(), degrees);
+            let left =
+                (self.image().width() as i32 - rotated.width() as i32) / 2;
+            let top =
+                (self.image().height() as i32 - rotated.height() as i32) / 2;
+            self.image().clear();
+            self.image().draw(&rotated, left, top);
+        }
+    }
+
+    pub fn shear_selection(&mut self, shear: Shear, vertical: bool) {
+        let shear_image = |image: &Image, anchor: i32| {
+            if vertical {
+                transform::shear_vert(image, shear, anchor)
+            } else {
+                transform::shear_horz(image, shear, anchor)
+            }
+        };
+        if let Some((ref mut image, ref mut position)) =
+            self.state.current.selection
+        {
+            let anchor = if vertical { 0 } else { image.height() as i32 - 1 };
+            let (sheared, offset) = shear_image(image, anchor);
+            *position = if vertical {
+                position.offset(0, offset)
+            } else {
+                position.offset(offset, 0)
+            };
+            *image = Rc::new(sheared);
+        } else {
+            let anchor =
+                if vertical { 0 } else { self.image().height() as i32 - 1 };
+            let (sheared, offset) = shear_image(self.image(), anchor);
+            self.image().clear();
+            if vertical {
+                self.image().draw(&sheared, 0, offset);
+            } else {
+                self.image().draw(&sheared, offset, 0);
+            }
+        }
+    }
+
+    pub fn italicize_font(&mut self, shear: Shear) {
+        self.unselect();
+        if let Data::AHF(ref mut ahf) = self.state.current.data {
+            let baseline = ahf.font.baseline();
+            let italicize = |glyph: &Glyph| {
+                let (image, offset) =
+                    transform::shear_horz(glyph.image(), shear, baseline);
+                Glyph::new(
+                    image,
+                    glyph.left_edge() - offset,
+                    glyph.right_edge() - offset,
+                )
+            };
+            let default_glyph = italicize(ahf.font.default_glyph());
+            ahf.font.set_default_glyph(default_glyph);
+            let chars: Vec<char> = ahf.font.chars().collect();
+            for chr in chars {
+                let new_glyph = italicize(&ahf.font[chr]);
+                ahf.font.set_char_glyph(chr, new_glyph);
+            }
+        }
+    }
+
+    pub fn scale_selection_up(&mut self, method: Upscale) {
+        let palette = self.state.palette().clone();
         if let Some((ref mut image, _)) = self.state.current.selection {
-            *image = Rc::new(scale_up(image, by));
+            *image = Rc::new(transform::upscale(image, &palette, method));
         } else {
-            let scaled = scale_up(self.image(), by);
+            let scaled = transform::upscale(self.image(), &palette, method);
             self.image().clear();
             self.image().draw(&scaled, 0, 0);
         }
@@ -921,6 +1711,60 @@ impl<'a> Mutation<'a> {
         }
     }
 
+    pub fn scale_selection_to(
+        &mut self,
+        width: u32,
+        height: u32,
+        method: Resample,
+    ) {
+        let palette = self.state.palette().clone();
+        if let Some((ref mut image, _)) = self.state.current.selection {
+            *image = Rc::new(transform::scale_to(
+                image, &palette, width, height, method,
+            ));
+        } else {
+            let scaled = transform::scale_to(
+                self.image(),
+                &palette,
+                width,
+                height,
+                method,
+            );
+            self.image().clear();
+            self.image().draw(&scaled, 0, 0);
+        }
+    }
+
+    pub fn outline_selection(&mut self, color: Color, diagonals: bool) {
+        if let Some((ref mut image, ref mut position)) =
+            self.state.current.selection
+        {
+            let margins = transform::outline_margins(image);
+            let grown = transform::grow(image, &margins);
+            *image = Rc::new(transform::outline(&grown, color, diagonals));
+            *position =
+                position.offset(-(margins.left as i32), -(margins.top as i32));
+        } else {
+            let outlined = transform::outline(self.image(), color, diagonals);
+            *self.image() = outlined;
+        }
+    }
+
+    pub fn drop_shadow_selection(&mut self, color: Color, dx: i32, dy: i32) {
+        if let Some((ref mut image, ref mut position)) =
+            self.state.current.selection
+        {
+            let margins = transform::shadow_margins(image, dx, dy);
+            let grown = transform::grow(image, &margins);
+            *image = Rc::new(transform::drop_shadow(&grown, color, dx, dy));
+            *position =
+                position.offset(-(margins.left as i32), -(margins.top as i32));
+        } else {
+            let shadowed = transform::drop_shadow(self.image(), color, dx, dy);
+            *self.image() = shadowed;
+        }
+    }
+
     pub fn delete_selection(&mut self) {
         self.state.current.selection = None;
     }
@@ -959,23 +1803,6 @@ impl<'a> Mutation<'a> {
 
 //===========================================================================//
 
-fn scale_up(image: &Image, by: u32) -> Image {
-    let mut scaled = Image::new(image.width() * by, image.height() * by);
-    for row in 0..image.height() {
-        for col in 0..image.width() {
-            let color = image[(col, row)];
-            scaled.fill_rect(
-                (by * col) as i32,
-                (by * row) as i32,
-                by,
-                by,
-                color,
-            );
-        }
-    }
-    scaled
-}
-
 fn scale_down(image: &Image, palette: &Palette, by: u32) -> Image {
     let mut scaled = Image::new((image.width() / by).max(1), (image.height() / by).max(1));
     for row in 0..scaled.height() {
@@ -0,0 +1,652 @@
+// +--------------------------------------------------------------------------+
+// | Copyright 2016 Matthew D. Steele <mdsteele@alum.mit.edu>                 |
+// |                                                                          |
+// | This file is part of Tuna.                                               |
+// |                                                                          |
+// | Tuna is free software: you can redistribute it and/or modify it under    |
+// | the terms of the GNU General Public License as published by the Free     |
+// | Software Foundation, either version 3 of the License, or (at your        |
+// | option) any later version.                                               |
+// |                                                                          |
+// | Tuna is distributed in the hope that it will be useful, but WITHOUT ANY  |
+// | WARRANTY; without even the implied warranty of MERCHANTABILITY or        |
+// | FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License    |
+// | for details.                                                             |
+// |                                                                          |
+// | You should have received a copy of the GNU General Public License along  |
+// | with Tuna.  If not, see <http://www.gnu.org/licenses/>.                  |
+// +--------------------------------------------------------------------------+
+
+use crate::util;
+use ahi::{Color, Image, Palette};
+use num_integer::{div_floor, mod_floor};
+use std::cmp;
+
+//===========================================================================//
+
+/// Which part of an image stays in place when it is resized.
+#[derive(Clone, Copy)]
+pub struct Anchor {
+    horz: i32,
+    vert: i32,
+}
+
+impl Anchor {
+    pub const TOP_LEFT: Anchor = Anchor { horz: 0, vert: 0 };
+
+    pub fn parse(text: &str) -> Option<Anchor> {
+        let (horz, vert) = match text {
+            "tl" => (0, 0),
+            "t" => (1, 0),
+            "tr" => (2, 0),
+            "l" => (0, 1),
+            "c" => (1, 1),
+            "r" => (2, 1),
+            "bl" => (0, 2),
+            "b" => (1, 2),
+            "br" => (2, 2),
+            _ => return None,
+        };
+        Some(Anchor { horz, vert })
+    }
+
+    /// Returns where the old image's top-left corner ends up within the
+    /// resized image.
+    pub fn offset(
+        &self,
+        (old_width, old_height): (u32, u32),
+        (new_width, new_height): (u32, u32),
+    ) -> (i32, i32) {
+        (
+            (new_width as i32 - old_width as i32) * self.horz / 2,
+            (new_height as i32 - old_height as i32) * self.vert / 2,
+        )
+    }
+}
+
+//===========================================================================//
+
+pub struct Margins {
+    pub left: u32,
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+}
+
+#[derive(Clone, Copy)]
+pub enum Resample {
+    Nearest,
+    Majority,
+    Average,
+}
+
+impl Resample {
+    pub fn name(self) -> &'static str {
+        match self {
+            Resample::Nearest => "nearest",
+            Resample::Majority => "majority",
+            Resample::Average => "average",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Resample> {
+        match name {
+            "nearest" => Some(Resample::Nearest),
+            "majority" => Some(Resample::Majority),
+            "average" => Some(Resample::Average),
+            _ => None,
+        }
+    }
+}
+
+//===========================================================================//
+
+/// A shear of `step` pixels for every `per` rows (or columns).
+#[derive(Clone, Copy)]
+pub struct Shear {
+    pub step: i32,
+    pub per: u32,
+}
+
+impl Shear {
+    pub fn parse(text: &str) -> Option<Shear> {
+        let pieces: Vec<&str> = text.trim().split('/').collect();
+        let step = pieces[0].trim().parse::<i32>().ok()?;
+        let per = match pieces.len() {
+            1 => 1,
+            2 => pieces[1].trim().parse::<u32>().ok()?,
+            _ => return None,
+        };
+        if per == 0 {
+            return None;
+        }
+        Some(Shear { step, per })
+    }
+
+    fn offset(&self, distance: i32) -> i32 {
+        div_floor(self.step * distance, self.per as i32)
+    }
+}
+
+//===========================================================================//
+
+#[derive(Clone, Copy)]
+pub enum Upscale {
+    Nearest(u32),
+    Epx,
+    Scale3x,
+    Xbr,
+}
+
+//===========================================================================//
+
+pub fn grow(image: &Image, margins: &Margins) -> Image {
+    let mut grown = Image::new(
+        image.width() + margins.left + margins.right,
+        image.height() + margins.top + margins.bottom,
+    );
+    grown.draw(image, margins.left as i32, margins.top as i32);
+    grown
+}
+
+pub fn resize(
+    image: &Image,
+    width: u32,
+    height: u32,
+    anchor: Anchor,
+    fill: Color,
+) -> Image {
+    let mut resized = Image::new(width, height);
+    copy_properties(image, &mut resized);
+    resized.fill_rect(0, 0, width, height, fill);
+    let (left, top) =
+        anchor.offset((image.width(), image.height()), (width, height));
+    for row in 0..image.height() {
+        for col in 0..image.width() {
+            let x = left + col as i32;
+            let y = top + row as i32;
+            if x >= 0 && x < width as i32 && y >= 0 && y < height as i32 {
+                resized[(x as u32, y as u32)] = image[(col, row)];
+            }
+        }
+    }
+    resized
+}
+
+pub fn crop_rect(
+    image: &Image,
+    left: i32,
+    top: i32,
+    width: u32,
+    height: u32,
+) -> Image {
+    let mut cropped = Image::new(width, height);
+    copy_properties(image, &mut cropped);
+    for row in 0..height {
+        for col in 0..width {
+            let x = left + col as i32;
+            let y = top + row as i32;
+            if x >= 0
+                && x < image.width() as i32
+                && y >= 0
+                && y < image.height() as i32
+            {
+                cropped[(col, row)] = image[(x as u32, y as u32)];
+            }
+        }
+    }
+    cropped
+}
+
+pub fn outline_margins(image: &Image) -> Margins {
+    match opaque_bounds(image) {
+        Some((min_x, min_y, max_x, max_y)) => Margins {
+            left: if min_x == 0 { 1 } else { 0 },
+            top: if min_y == 0 { 1 } else { 0 },
+            right: if max_x + 1 == image.width() as i32 { 1 } else { 0 },
+            bottom: if max_y + 1 == image.height() as i32 { 1 } else { 0 },
+        },
+        None => Margins { left: 0, top: 0, right: 0, bottom: 0 },
+    }
+}
+
+pub fn outline(image: &Image, color: Color, diagonals: bool) -> Image {
+    let width = image.width() as i32;
+    let height = image.height() as i32;
+    let mut outlined = image.clone();
+    for row in 0..height {
+        for col in 0..width {
+            if image[(col as u32, row as u32)] != Color::C0 {
+                continue;
+            }
+            let mut neighbors = vec![(-1, 0), (1, 0), (0, -1), (0, 1)];
+            if diagonals {
+                neighbors.extend(&[(-1, -1), (1, -1), (-1, 1), (1, 1)]);
+            }
+            let touches_opaque = neighbors.into_iter().any(|(dx, dy)| {
+                let x = col + dx;
+                let y = row + dy;
+                x >= 0
+                    && x < width
+                    && y >= 0
+                    && y < height
+                    && image[(x as u32, y as u32)] != Color::C0
+            });
+            if touches_opaque {
+                outlined[(col as u32, row as u32)] = color;
+            }
+        }
+    }
+    outlined
+}
+
+pub fn shadow_margins(image: &Image, dx: i32, dy: i32) -> Margins {
+    match opaque_bounds(image) {
+        Some((min_x, min_y, max_x, max_y)) => Margins {
+            left: cmp::max(0, -(min_x + dx)) as u32,
+            top: cmp::max(0, -(min_y + dy)) as u32,
+            right: cmp::max(0, max_x + dx + 1 - image.width() as i32) as u32,
+            bottom: cmp::max(0, max_y + dy + 1 - image.height() as i32) as u32,
+        },
+        None => Margins { left: 0, top: 0, right: 0, bottom: 0 },
+    }
+}
+
+pub fn drop_shadow(image: &Image, color: Color, dx: i32, dy: i32) -> Image {
+    let width = image.width() as i32;
+    let height = image.height() as i32;
+    let mut shadowed = image.clone();
+    for row in 0..height {
+        for col in 0..width {
+            let x = col + dx;
+            let y = row + dy;
+            if image[(col as u32, row as u32)] != Color::C0
+                && x >= 0
+                && x < width
+                && y >= 0
+                && y < height
+                && image[(x as u32, y as u32)] == Color::C0
+            {
+                shadowed[(x as u32, y as u32)] = color;
+            }
+        }
+    }
+    shadowed
+}
+
+/// Shears the image horizontally, so that each row is shifted right by the
+/// shear offset for its distance above `anchor_row`.  Returns the sheared
+/// image, along with the x-position of its left edge relative to the original
+/// image.
+pub fn shear_horz(
+    image: &Image,
+    shear: Shear,
+    anchor_row: i32,
+) -> (Image, i32) {
+    let height = image.height() as i32;
+    let offsets: Vec<i32> =
+        (0..height).map(|row| shear.offset(anchor_row - row)).collect();
+    let min_offset = offsets.iter().cloned().min().unwrap_or(0);
+    let max_offset = offsets.iter().cloned().max().unwrap_or(0);
+    let mut sheared = Image::new(
+        image.width() + (max_offset - min_offset) as u32,
+        image.height(),
+    );
+    for row in 0..image.height() {
+        let shift = (offsets[row as usize] - min_offset) as u32;
+        for col in 0..image.width() {
+            sheared[(col + shift, row)] = image[(col, row)];
+        }
+    }
+    (sheared, min_offset)
+}
+
+/// Shears the image vertically, so that each column is shifted down by the
+/// shear offset for its distance right of `anchor_col`.  Returns the sheared
+/// image, along with the y-position of its top edge relative to the original
+/// image.
+pub fn shear_vert(
+    image: &Image,
+    shear: Shear,
+    anchor_col: i32,
+) -> (Image, i32) {
+    let width = image.width() as i32;
+    let offsets: Vec<i32> =
+        (0..width).map(|col| shear.offset(col - anchor_col)).collect();
+    let min_offset = offsets.iter().cloned().min().unwrap_or(0);
+    let max_offset = offsets.iter().cloned().max().unwrap_or(0);
+    let mut sheared = Image::new(
+        image.width(),
+        image.height() + (max_offset - min_offset) as u32,
+    );
+    for col in 0..image.width() {
+        let shift = (offsets[col as usize] - min_offset) as u32;
+        for row in 0..image.height() {
+            sheared[(col, row + shift)] = image[(col, row)];
+        }
+    }
+    (sheared, min_offset)
+}
+
+pub fn wrap_shift(image: &Image, dx: i32, dy: i32) -> Image {
+    let width = image.width() as i32;
+    let height = image.height() as i32;
+    let mut shifted = Image::new(image.width(), image.height());
+    copy_properties(image, &mut shifted);
+    for row in 0..height {
+        for col in 0..width {
+            let x = mod_floor(col + dx, width) as u32;
+            let y = mod_floor(row + dy, height) as u32;
+            shifted[(x, y)] = image[(col as u32, row as u32)];
+        }
+    }
+    shifted
+}
+
+pub fn upscale(image: &Image, palette: &Palette, method: Upscale) -> Image {
+    match method {
+        Upscale::Nearest(by) => scale_nearest(image, by),
+        Upscale::Epx => scale_epx(image),
+        Upscale::Scale3x => scale_3x(image),
+        Upscale::Xbr => scale_xbr(image, palette),
+    }
+}
+
+fn scale_nearest(image: &Image, by: u32) -> Image {
+    let mut scaled = Image::new(image.width() * by, image.height() * by);
+    for row in 0..image.height() {
+        for col in 0..image.width() {
+            let color = image[(col, row)];
+            scaled.fill_rect(
+                (by * col) as i32,
+                (by * row) as i32,
+                by,
+                by,
+                color,
+            );
+        }
+    }
+    scaled
+}
+
+fn scale_epx(image: &Image) -> Image {
+    let mut scaled = Image::new(image.width() * 2, image.height() * 2);
+    for row in 0..image.height() {
+        for col in 0..image.width() {
+            let (x, y) = (col as i32, row as i32);
+            let p = image[(col, row)];
+            let a = clamped_pixel(image, x, y - 1);
+            let b = clamped_pixel(image, x + 1, y);
+            let c = clamped_pixel(image, x - 1, y);
+            let d = clamped_pixel(image, x, y + 1);
+            let mut block = [p, p, p, p];
+            if c == a && c != d && a != b {
+                block[0] = a;
+            }
+            if a == b && a != c && b != d {
+                block[1] = b;
+            }
+            if d == c && d != b && c != a {
+                block[2] = c;
+            }
+            if b == d && b != a && d != c {
+                block[3] = d;
+            }
+            for (index, &color) in block.iter().enumerate() {
+                let dx = (index % 2) as u32;
+                let dy = (index / 2) as u32;
+                scaled[(2 * col + dx, 2 * row + dy)] = color;
+            }
+        }
+    }
+    scaled
+}
+
+fn scale_3x(image: &Image) -> Image {
+    let mut scaled = Image::new(image.width() * 3, image.height() * 3);
+    for row in 0..image.height() {
+        for col in 0..image.width() {
+            let (x, y) = (col as i32, row as i32);
+            let a = clamped_pixel(image, x - 1, y - 1);
+            let b = clamped_pixel(image, x, y - 1);
+            let c = clamped_pixel(image, x + 1, y - 1);
+            let d = clamped_pixel(image, x - 1, y);
+            let e = image[(col, row)];
+            let f = clamped_pixel(image, x + 1, y);
+            let g = clamped_pixel(image, x - 1, y + 1);
+            let h = clamped_pixel(image, x, y + 1);
+            let i = clamped_pixel(image, x + 1, y + 1);
+            let mut block = [e; 9];
+            if b != h && d != f {
+                if d == b {
+                    block[0] = d;
+                }
+                if (d == b && e != c) || (b == f && e != a) {
+                    block[1] = b;
+                }
+                if b == f {
+                    block[2] = f;
+                }
+                if (d == b && e != g) || (d == h && e != a) {
+                    block[3] = d;
+                }
+                if (b == f && e != i) || (h == f && e != c) {
+                    block[5] = f;
+                }
+                if d == h {
+                    block[6] = d;
+                }
+                if (d == h && e != i) || (h == f && e != g) {
+                    block[7] = h;
+                }
+                if h == f {
+                    block[8] = f;
+                }
+            }
+            for (index, &color) in block.iter().enumerate() {
+                let dx = (index % 3) as u32;
+                let dy = (index / 3) as u32;
+                scaled[(3 * col + dx, 3 * row + dy)] = color;
+            }
+        }
+    }
+    scaled
+}
+
+/// Scales the image by 2x using the first-level xBR edge rule, without any
+/// blending, so that the result only uses colors from the original image.
+fn scale_xbr(image: &Image, palette: &Palette) -> Image {
+    let mut scaled = scale_nearest(image, 2);
+    for row in 0..image.height() {
+        for col in 0..image.width() {
+            for &(sx, sy) in &[(-1, -1), (1, -1), (-1, 1), (1, 1)] {
+                let (x, y) = (col as i32, row as i32);
+                let pixel = |dx: i32, dy: i32| {
+                    clamped_pixel(image, x + sx * dx, y + sy * dy)
+                };
+                let dist =
+                    |c1: Color, c2: Color| color_distance(palette, c1, c2);
+                let e = pixel(0, 0);
+                let f = pixel(1, 0);
+                let h = pixel(0, 1);
+                let i = pixel(1, 1);
+                let edge = dist(e, pixel(1, -1))
+                    + dist(e, pixel(-1, 1))
+                    + dist(i, pixel(2, 0))
+                    + dist(i, pixel(0, 2))
+                    + 4 * dist(h, f);
+                let across = dist(h, pixel(-1, 0))
+                    + dist(h, pixel(1, 2))
+                    + dist(f, pixel(2, 1))
+                    + dist(f, pixel(0, -1))
+                    + 4 * dist(e, i);
+                if edge < across {
+                    let color = if dist(e, f) <= dist(e, h) { f } else { h };
+                    let dx = if sx > 0 { 1 } else { 0 };
+                    let dy = if sy > 0 { 1 } else { 0 };
+                    scaled[(2 * col + dx, 2 * row + dy)] = color;
+                }
+            }
+        }
+    }
+    scaled
+}
+
+pub fn scale_to(
+    image: &Image,
+    palette: &Palette,
+    width: u32,
+    height: u32,
+    method: Resample,
+) -> Image {
+    let mut scaled = Image::new(width, height);
+    let source_range = |index: u32, size: u32, new_size: u32| {
+        let start = index * size / new_size;
+        let end = ((index + 1) * size).div_ceil(new_size);
+        start..cmp::max(end, start + 1)
+    };
+    for row in 0..height {
+        for col in 0..width {
+            let nearest = image[(
+                (2 * col + 1) * image.width() / (2 * width),
+                (2 * row + 1) * image.height() / (2 * height),
+            )];
+            let mut samples = Vec::new();
+            for y in source_range(row, image.height(), height) {
+                for x in source_range(col, image.width(), width) {
+                    samples.push(image[(x, y)]);
+                }
+            }
+            scaled[(col, row)] = match method {
+                Resample::Nearest => nearest,
+                Resample::Majority => majority_color(&samples, nearest),
+                Resample::Average => average_color(palette, &samples),
+            };
+        }
+    }
+    scaled
+}
+
+/// Rotates the image clockwise by the given angle, RotSprite-style: the image
+/// is first upscaled 8x with EPX, then each output pixel takes a majority vote
+/// of the rotated upscaled pixels it covers.  The result is grown to fit the
+/// rotated image, and never contains colors that weren't in the original.
+pub fn rotsprite(image: &Image, degrees: f64) -> Image {
+    let big = scale_epx(&scale_epx(&scale_epx(image)));
+    let width = image.width() as f64;
+    let height = image.height() as f64;
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let new_width = (width * cos.abs() + height * sin.abs() - 1e-6).ceil();
+    let new_height = (width * sin.abs() + height * cos.abs() - 1e-6).ceil();
+    let mut rotated =
+        Image::new(new_width.max(1.0) as u32, new_height.max(1.0) as u32);
+    let sample = |x: f64, y: f64| {
+        let cx = x - 0.5 * new_width;
+        let cy = y - 0.5 * new_height;
+        let sx = (cx * cos + cy * sin + 0.5 * width) * 8.0;
+        let sy = (cy * cos - cx * sin + 0.5 * height) * 8.0;
+        if sx >= 0.0
+            && sx < big.width() as f64
+            && sy >= 0.0
+            && sy < big.height() as f64
+        {
+            big[(sx as u32, sy as u32)]
+        } else {
+            Color::C0
+        }
+    };
+    for row in 0..rotated.height() {
+        for col in 0..rotated.width() {
+            let mut samples = Vec::with_capacity(64);
+            for sub_row in 0..8 {
+                for sub_col in 0..8 {
+                    samples.push(sample(
+                        col as f64 + (sub_col as f64 + 0.5) / 8.0,
+                        row as f64 + (sub_row as f64 + 0.5) / 8.0,
+                    ));
+                }
+            }
+            let center = sample(col as f64 + 0.5, row as f64 + 0.5);
+            rotated[(col, row)] = majority_color(&samples, center);
+        }
+    }
+    rotated
+}
+
+//===========================================================================//
+
+fn copy_properties(from: &Image, to: &mut Image) {
+    to.set_tag(from.tag().to_string());
+    to.set_metadata(from.metadata().clone());
+}
+
+fn majority_color(samples: &[Color], preferred: Color) -> Color {
+    let mut counts = [0usize; 16];
+    for &color in samples {
+        counts[util::color_index(color)] += 1;
+    }
+    let best = (0..16).max_by_key(|&index| counts[index]).unwrap();
+    if counts[util::color_index(preferred)] == counts[best] {
+        preferred
+    } else {
+        util::color_at_index(best)
+    }
+}
+
+fn average_color(palette: &Palette, samples: &[Color]) -> Color {
+    let mut totals = [0u32; 4];
+    for &color in samples {
+        let (r, g, b, a) = palette[color];
+        totals[0] += r as u32;
+        totals[1] += g as u32;
+        totals[2] += b as u32;
+        totals[3] += a as u32;
+    }
+    let count = samples.len() as u32;
+    let rgba = (
+        (totals[0] / count) as u8,
+        (totals[1] / count) as u8,
+        (totals[2] / count) as u8,
+        (totals[3] / count) as u8,
+    );
+    util::nearest_color(palette, rgba)
+}
+
+fn clamped_pixel(image: &Image, x: i32, y: i32) -> Color {
+    let x = cmp::max(0, cmp::min(x, image.width() as i32 - 1));
+    let y = cmp::max(0, cmp::min(y, image.height() as i32 - 1));
+    image[(x as u32, y as u32)]
+}
+
+fn color_distance(palette: &Palette, color1: Color, color2: Color) -> u32 {
+    let (r1, g1, b1, a1) = palette[color1];
+    let (r2, g2, b2, a2) = palette[color2];
+    let diff = |c1: u8, c2: u8| (c1 as i32 - c2 as i32).unsigned_abs();
+    diff(r1, r2) + diff(g1, g2) + diff(b1, b2) + diff(a1, a2)
+}
+
+/// Returns the (min_x, min_y, max_x, max_y) bounds of the image's
+/// non-transparent pixels, or `None` if the image is entirely transparent.
+pub fn opaque_bounds(image: &Image) -> Option<(i32, i32, i32, i32)> {
+    let mut bounds: Option<(i32, i32, i32, i32)> = None;
+    for row in 0..image.height() {
+        for col in 0..image.width() {
+            if image[(col, row)] == Color::C0 {
+                continue;
+            }
+            let (x, y) = (col as i32, row as i32);
+            bounds = Some(match bounds {
+                Some((min_x, min_y, max_x, max_y)) => (
+                    cmp::min(min_x, x),
+                    cmp::min(min_y, y),
+                    cmp::max(max_x, x),
+                    cmp::max(max_y, y),
+                ),
+                None => (x, y, x, y),
+            });
+        }
+    }
+    bounds
+}
+
+//===========================================================================//
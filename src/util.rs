@@ -43,6 +43,14 @@ const COLORS: &[ahi::Color] = &[
 
 //===========================================================================//
 
+pub fn color_index(color: ahi::Color) -> usize {
+    COLORS.iter().position(|&c| c == color).unwrap()
+}
+
+pub fn color_at_index(index: usize) -> ahi::Color {
+    COLORS[index]
+}
+
 pub fn load_ahf_from_file(path: &String) -> io::Result<ahi::Font> {
     let mut file = File::open(path)?;
     ahi::Font::read(&mut file)
@@ -76,6 +84,28 @@ pub fn load_png_from_file(
     palette: &ahi::Palette,
     path: &String,
 ) -> io::Result<ahi::Image> {
+    let (width, height, rgba_data) = load_png_rgba_from_file(path)?;
+    let mut image = ahi::Image::new(width, height);
+    for row in 0..height {
+        for col in 0..width {
+            let start = ((row * width + col) as usize) * 4;
+            let png_rgba: (u8, u8, u8, u8) = (
+                rgba_data[start + 0],
+                rgba_data[start + 1],
+                rgba_data[start + 2],
+                rgba_data[start + 3],
+            );
+            image[(col, row)] = nearest_color(palette, png_rgba);
+        }
+    }
+    Ok(image)
+}
+
+/// Loads a PNG file as full-color RGBA data, returning its width, height and
+/// pixel bytes (four per pixel, row-major).
+pub fn load_png_rgba_from_file(
+    path: &String,
+) -> io::Result<(u32, u32, Vec<u8>)> {
     let decoder = png::Decoder::new(File::open(path)?);
     let mut reader = decoder.read_info()?;
     let mut buffer = vec![0; reader.output_buffer_size()];
@@ -121,20 +151,114 @@ pub fn load_png_from_file(
             ));
         }
     };
-    let mut image = ahi::Image::new(info.width, info.height);
-    for row in 0..info.height {
-        for col in 0..info.width {
-            let start = ((row * info.width + col) as usize) * 4;
-            let png_rgba: (u8, u8, u8, u8) = (
-                rgba_data[start + 0],
-                rgba_data[start + 1],
-                rgba_data[start + 2],
-                rgba_data[start + 3],
-            );
-            image[(col, row)] = nearest_color(palette, png_rgba);
+    Ok((info.width, info.height, rgba_data))
+}
+
+pub fn parse_rgba_hex(text: &str) -> Option<(u8, u8, u8, u8)> {
+    match (text.len(), u32::from_str_radix(text, 16)) {
+        (0, _) => Some((0, 0, 0, 0)),
+        (1, Ok(v)) => {
+            let gray = (0x11 * v) as u8;
+            Some((gray, gray, gray, 255))
+        }
+        (2, Ok(v)) => {
+            let gray = v as u8;
+            Some((gray, gray, gray, 255))
+        }
+        (3, Ok(v)) => {
+            let r = (0x11 * (0xf & (v >> 8))) as u8;
+            let g = (0x11 * (0xf & (v >> 4))) as u8;
+            let b = (0x11 * (0xf & v)) as u8;
+            Some((r, g, b, 255))
+        }
+        (4, Ok(v)) => {
+            let r = (0x11 * (0xf & (v >> 12))) as u8;
+            let g = (0x11 * (0xf & (v >> 8))) as u8;
+            let b = (0x11 * (0xf & (v >> 4))) as u8;
+            let a = (0x11 * (0xf & v)) as u8;
+            Some((r, g, b, a))
+        }
+        (6, Ok(v)) => {
+            let r = (0xff & (v >> 16)) as u8;
+            let g = (0xff & (v >> 8)) as u8;
+            let b = (0xff & v) as u8;
+            Some((r, g, b, 255))
+        }
+        (8, Ok(v)) => {
+            let r = (0xff & (v >> 24)) as u8;
+            let g = (0xff & (v >> 16)) as u8;
+            let b = (0xff & (v >> 8)) as u8;
+            let a = (0xff & v) as u8;
+            Some((r, g, b, a))
         }
+        _ => None,
     }
-    Ok(image)
+}
+
+/// Converts an RGB color to HSV, with hue in degrees [0, 360) and saturation
+/// and value in [0, 1].
+pub fn rgb_to_hsv(rgb: (u8, u8, u8)) -> (f64, f64, f64) {
+    let r = rgb.0 as f64 / 255.0;
+    let g = rgb.1 as f64 / 255.0;
+    let b = rgb.2 as f64 / 255.0;
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let sat = if max == 0.0 { 0.0 } else { delta / max };
+    (hue.rem_euclid(360.0), sat, max)
+}
+
+pub fn hsv_to_rgb(hsv: (f64, f64, f64)) -> (u8, u8, u8) {
+    let hue = hsv.0.rem_euclid(360.0) / 60.0;
+    let sat = hsv.1.clamp(0.0, 1.0);
+    let val = hsv.2.clamp(0.0, 1.0);
+    let chroma = val * sat;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = val - chroma;
+    let to_u8 = |c: f64| ((c + m) * 255.0).round() as u8;
+    (to_u8(r), to_u8(g), to_u8(b))
+}
+
+/// Interpolates between two colors in HSV space, taking the shorter way
+/// around the hue circle.  The hue is additionally pushed by up to
+/// `hue_shift` degrees towards the middle of the ramp.
+pub fn interpolate_color(
+    start: (u8, u8, u8, u8),
+    end: (u8, u8, u8, u8),
+    t: f64,
+    hue_shift: f64,
+) -> (u8, u8, u8, u8) {
+    let (mut hue0, sat0, val0) = rgb_to_hsv((start.0, start.1, start.2));
+    let (mut hue1, sat1, val1) = rgb_to_hsv((end.0, end.1, end.2));
+    if sat0 == 0.0 {
+        hue0 = hue1;
+    } else if sat1 == 0.0 {
+        hue1 = hue0;
+    }
+    let delta = (hue1 - hue0 + 540.0).rem_euclid(360.0) - 180.0;
+    let hue = hue0 + delta * t + hue_shift * 4.0 * t * (1.0 - t);
+    let sat = sat0 + (sat1 - sat0) * t;
+    let val = val0 + (val1 - val0) * t;
+    let (r, g, b) = hsv_to_rgb((hue, sat, val));
+    let alpha = (start.3 as f64) + ((end.3 as f64) - (start.3 as f64)) * t;
+    (r, g, b, alpha.round() as u8)
 }
 
 pub fn nearest_color(palette: &ahi::Palette, rgba: (u8, u8, u8, u8)) -> ahi::Color {
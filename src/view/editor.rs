@@ -17,26 +17,37 @@
 // | with Tuna.  If not, see <http://www.gnu.org/licenses/>.                  |
 // +--------------------------------------------------------------------------+
 
+use super::layers::LayersView;
 use super::menu::{MenuAction, MenuView};
 use super::metadata::MetadataView;
 use super::mirrors::Mirrors;
 use super::palette::{PaletteAction, PaletteView};
+use super::rulers::Rulers;
 use super::scrollbar::ImagesScrollbar;
 use super::textbox::{ModalTextBox, Mode};
 use super::tiles::TileView;
 use super::toolbox::Toolbox;
 use super::unsaved::UnsavedIndicator;
 use crate::canvas::{Canvas, Resources};
+use crate::dither::Dither;
 use crate::element::{Action, AggregateElement, GuiElement, SubrectElement};
-use crate::event::{Event, Keycode, COMMAND, SHIFT};
+use crate::event::{Event, Keycode, ALT, COMMAND, SHIFT};
+use crate::grid::Grid;
 use crate::paint::ImageCanvas;
+use crate::reference::Reference;
+use crate::sidecar;
 use crate::state::EditorState;
+use crate::transform::{Anchor, Resample, Shear, Upscale};
 use crate::util;
 use ahi::Color;
 use sdl2::rect::{Point, Rect};
 
 //===========================================================================//
 
+const MAX_METADATA_SLOT: usize = 256;
+
+//===========================================================================//
+
 pub struct EditorView {
     aggregate: AggregateElement<EditorState, ()>,
     menu: MenuView,
@@ -45,25 +56,27 @@ pub struct EditorView {
 }
 
 impl EditorView {
-    pub const WIDTH: u32 = 480;
-    pub const HEIGHT: u32 = 320;
+    pub const WIDTH: u32 = 502;
+    pub const HEIGHT: u32 = 416;
 
     pub fn new(offset: Point) -> SubrectElement<EditorView> {
         let elements: Vec<Box<dyn GuiElement<EditorState, ()>>> = vec![
             Box::new(UnsavedIndicator::new(4, 11)),
             Box::new(Toolbox::new(3, 34)),
-            Box::new(Mirrors::new(3, 134)),
-            Box::new(ImagesScrollbar::new(440, 34)),
-            Box::new(ImageCanvas::new(80, 36, 256)),
-            Box::new(ImageCanvas::new(348, 36, 64)),
-            Box::new(TileView::new(341, 126, 96, 96)),
-            Box::new(MetadataView::new(348, 230)),
+            Box::new(Mirrors::new(3, 206)),
+            Box::new(ImagesScrollbar::new(462, 34)),
+            Box::new(Rulers::new(102, 48, 256)),
+            Box::new(ImageCanvas::new(102, 48, 256)),
+            Box::new(ImageCanvas::new(370, 36, 64)),
+            Box::new(TileView::new(363, 126, 96, 96)),
+            Box::new(MetadataView::new(370, 230)),
+            Box::new(LayersView::new(363, 292, 96, 96)),
         ];
         SubrectElement::new(
             EditorView {
                 aggregate: AggregateElement::new(elements),
-                menu: MenuView::new(8, 297),
-                palette: PaletteView::new(3, 188),
+                menu: MenuView::new(8, 393),
+                palette: PaletteView::new(3, 284),
                 textbox: ModalTextBox::new(20, 10),
             },
             Rect::new(
@@ -80,6 +93,15 @@ impl EditorView {
         self.textbox.set_mode(mode, text);
     }
 
+    fn begin_drop_shadow(&mut self) -> bool {
+        if self.textbox.mode() == Mode::Edit {
+            self.set_textbox_mode(Mode::DropShadow, "1,1".to_string());
+            true
+        } else {
+            false
+        }
+    }
+
     fn begin_export(&mut self, state: &mut EditorState) -> bool {
         if self.textbox.mode() == Mode::Edit {
             state.unselect_if_necessary();
@@ -109,6 +131,30 @@ impl EditorView {
         }
     }
 
+    fn begin_italicize(&mut self, state: &mut EditorState) -> bool {
+        if self.textbox.mode() == Mode::Edit && state.font().is_some() {
+            self.set_textbox_mode(Mode::Italicize, "1/3".to_string());
+            true
+        } else {
+            false
+        }
+    }
+
+    fn begin_set_mirror_axes(&mut self, state: &mut EditorState) -> bool {
+        if self.textbox.mode() == Mode::Edit {
+            let (axis_x, axis_y) = state.mirror_axes();
+            let format_axis =
+                |axis: i32| format!("{}", (axis + 1) as f64 / 2.0);
+            self.set_textbox_mode(
+                Mode::MirrorAxes,
+                format!("{},{}", format_axis(axis_x), format_axis(axis_y)),
+            );
+            true
+        } else {
+            false
+        }
+    }
+
     fn begin_new_image(&mut self, state: &mut EditorState) -> bool {
         if state.font().is_some() {
             if self.textbox.mode() == Mode::Edit {
@@ -123,6 +169,22 @@ impl EditorView {
         }
     }
 
+    fn begin_scale_to(&mut self, state: &mut EditorState) -> bool {
+        if self.textbox.mode() == Mode::Edit {
+            let (width, height) = match state.selection() {
+                Some((image, _)) => (image.width(), image.height()),
+                None => (state.image().width(), state.image().height()),
+            };
+            self.set_textbox_mode(
+                Mode::ScaleTo,
+                format!("{}x{} {}", width, height, Resample::Majority.name()),
+            );
+            true
+        } else {
+            false
+        }
+    }
+
     fn begin_set_color(
         &mut self,
         state: &mut EditorState,
@@ -139,6 +201,24 @@ impl EditorView {
         }
     }
 
+    fn begin_generate_ramp(&mut self, state: &mut EditorState) -> bool {
+        if self.textbox.mode() == Mode::Edit
+            && state.palette_index() < state.num_palettes()
+        {
+            state.unselect_if_necessary();
+            let start = util::color_index(state.color());
+            let end = match state.secondary_color() {
+                Some(color) => util::color_index(color),
+                None => 15,
+            };
+            let text = format!("{:X}-{:X}", start, end);
+            self.set_textbox_mode(Mode::GenerateRamp, text);
+            true
+        } else {
+            false
+        }
+    }
+
     fn begin_goto(&mut self, state: &mut EditorState) -> bool {
         if self.textbox.mode() == Mode::Edit {
             state.unselect_if_necessary();
@@ -149,10 +229,21 @@ impl EditorView {
         }
     }
 
+    fn begin_set_dither(&mut self, state: &mut EditorState) -> bool {
+        if self.textbox.mode() == Mode::Edit {
+            let dither = state.dither();
+            let cells = dither.size() * dither.size();
+            let text = format!("{}/{}", dither.level(), cells);
+            self.set_textbox_mode(Mode::SetDither, text);
+            true
+        } else {
+            false
+        }
+    }
+
     fn begin_set_grid(&mut self, state: &mut EditorState) -> bool {
         if self.textbox.mode() == Mode::Edit {
-            let (horz, vert) = state.grid();
-            let text = format!("{}x{}", horz, vert);
+            let text = state.grid().format();
             self.set_textbox_mode(Mode::SetGrid, text);
             true
         } else {
@@ -173,13 +264,57 @@ impl EditorView {
         }
     }
 
-    fn begin_resize(&mut self, state: &mut EditorState) -> bool {
+    fn begin_offset(&mut self, all: bool) -> bool {
+        if self.textbox.mode() == Mode::Edit {
+            let mode = if all { Mode::OffsetAll } else { Mode::Offset };
+            self.set_textbox_mode(mode, "0,0".to_string());
+            true
+        } else {
+            false
+        }
+    }
+
+    fn begin_set_onion_skin(&mut self, state: &mut EditorState) -> bool {
+        if self.textbox.mode() == Mode::Edit {
+            let text = format!("{}", state.onion_skin());
+            self.set_textbox_mode(Mode::OnionSkin, text);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn begin_load_reference(&mut self, state: &mut EditorState) -> bool {
+        if self.textbox.mode() == Mode::Edit {
+            let mut dir_path = state.filepath().to_string();
+            while !dir_path.is_empty() && !dir_path.ends_with("/") {
+                dir_path.pop();
+            }
+            self.set_textbox_mode(Mode::Reference, dir_path);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn begin_adjust_reference(&mut self, state: &mut EditorState) -> bool {
+        if self.textbox.mode() == Mode::Edit {
+            if let Some(reference) = state.reference() {
+                let text = reference.settings_string();
+                self.set_textbox_mode(Mode::ReferenceSettings, text);
+                return true;
+            }
+        }
+        false
+    }
+
+    fn begin_resize(&mut self, state: &mut EditorState, all: bool) -> bool {
         if self.textbox.mode() == Mode::Edit {
             state.unselect_if_necessary();
             self.set_textbox_mode(
-                Mode::Resize,
+                if all { Mode::Resize } else { Mode::ResizeCurrent },
                 format!(
-                    "{}x{}",
+                    "{}x{}@tl",
                     state.image().width(),
                     state.image().height()
                 ),
@@ -190,6 +325,15 @@ impl EditorView {
         }
     }
 
+    fn begin_rotate(&mut self) -> bool {
+        if self.textbox.mode() == Mode::Edit {
+            self.set_textbox_mode(Mode::Rotate, "45".to_string());
+            true
+        } else {
+            false
+        }
+    }
+
     fn begin_save_as(&mut self, state: &mut EditorState) -> bool {
         if self.textbox.mode() == Mode::Edit {
             state.unselect_if_necessary();
@@ -236,6 +380,21 @@ impl EditorView {
         }
     }
 
+    fn begin_set_ramps(&mut self, state: &mut EditorState) -> bool {
+        if self.textbox.mode() == Mode::Edit {
+            let text = state
+                .ramps()
+                .iter()
+                .map(|ramp| sidecar::format_ramp(ramp))
+                .collect::<Vec<String>>()
+                .join(" ");
+            self.set_textbox_mode(Mode::SetRamps, text);
+            true
+        } else {
+            false
+        }
+    }
+
     fn begin_set_tag(&mut self, state: &mut EditorState) -> bool {
         if self.textbox.mode() == Mode::Edit {
             state.unselect_if_necessary();
@@ -249,6 +408,17 @@ impl EditorView {
         }
     }
 
+    fn begin_shear(&mut self, vertical: bool) -> bool {
+        if self.textbox.mode() == Mode::Edit {
+            let mode =
+                if vertical { Mode::ShearVert } else { Mode::ShearHorz };
+            self.set_textbox_mode(mode, "1/2".to_string());
+            true
+        } else {
+            false
+        }
+    }
+
     fn begin_set_test_sentence(&mut self, state: &mut EditorState) -> bool {
         if self.textbox.mode() == Mode::Edit && state.font().is_some() {
             self.set_textbox_mode(
@@ -261,17 +431,61 @@ impl EditorView {
         }
     }
 
+    fn begin_trim(&mut self, state: &mut EditorState, all: bool) -> bool {
+        if state.font().is_some() {
+            state.mutation().trim_images(all, None)
+        } else if self.textbox.mode() == Mode::Edit {
+            let mode = if all { Mode::TrimAll } else { Mode::Trim };
+            self.set_textbox_mode(mode, "0,1".to_string());
+            true
+        } else {
+            false
+        }
+    }
+
     fn finish_mode(
         &mut self,
         state: &mut EditorState,
         mode: Mode,
         text: String,
     ) -> bool {
+        let edits_active_layer = matches!(
+            mode,
+            Mode::DropShadow
+                | Mode::Offset
+                | Mode::ResizeCurrent
+                | Mode::Rotate
+                | Mode::ScaleTo
+                | Mode::ShearHorz
+                | Mode::ShearVert
+                | Mode::Trim
+        );
+        if edits_active_layer && !state.active_layer_editable() {
+            // The active layer was locked or hidden while the mode was open.
+            return true;
+        }
         match mode {
+            Mode::DropShadow => {
+                let pieces: Vec<&str> = text.split(',').collect();
+                if pieces.len() != 2 {
+                    return false;
+                }
+                let dx = match pieces[0].trim().parse::<i32>() {
+                    Ok(dx) => dx,
+                    Err(_) => return false,
+                };
+                let dy = match pieces[1].trim().parse::<i32>() {
+                    Ok(dy) => dy,
+                    Err(_) => return false,
+                };
+                let color = state.color();
+                state.mutation().drop_shadow_selection(color, dx, dy);
+                true
+            }
             Mode::Edit => false,
             Mode::Export => {
                 match util::save_png_to_file(
-                    state.image(),
+                    &state.composite_image(),
                     state.palette(),
                     &text,
                 ) {
@@ -282,6 +496,12 @@ impl EditorView {
                     }
                 }
             }
+            Mode::GenerateRamp => match parse_ramp_endpoints(&text) {
+                Some((start, end, hue_shift)) => {
+                    state.mutation().generate_ramp(start, end, hue_shift)
+                }
+                None => false,
+            },
             Mode::Goto => state.go_to(&text),
             Mode::Import => {
                 match util::load_png_from_file(state.palette(), &text) {
@@ -292,6 +512,13 @@ impl EditorView {
                     }
                 }
             }
+            Mode::Italicize => match Shear::parse(&text) {
+                Some(shear) => {
+                    state.mutation().italicize_font(shear);
+                    true
+                }
+                None => false,
+            },
             Mode::LoadFile => match util::load_ahi_from_file(&text) {
                 Ok(collection) => {
                     state.load_collection(text, collection);
@@ -305,11 +532,102 @@ impl EditorView {
                     Err(_) => false,
                 },
             },
+            Mode::MirrorAxes => {
+                if text.trim().is_empty() {
+                    state.set_mirror_axes(None);
+                    return true;
+                }
+                let pieces: Vec<&str> = text.split(',').collect();
+                if pieces.len() != 2 {
+                    return false;
+                }
+                let axis_x = match pieces[0].trim().parse::<f64>() {
+                    Ok(x) if x.is_finite() => (2.0 * x).round() as i32 - 1,
+                    _ => return false,
+                };
+                let axis_y = match pieces[1].trim().parse::<f64>() {
+                    Ok(y) if y.is_finite() => (2.0 * y).round() as i32 - 1,
+                    _ => return false,
+                };
+                state.set_mirror_axes(Some((axis_x, axis_y)));
+                true
+            }
             Mode::NewGlyph => {
                 let chars: Vec<char> = text.chars().collect();
                 chars.len() == 1 && state.mutation().add_new_image(chars[0])
             }
-            Mode::Resize => {
+            Mode::Offset | Mode::OffsetAll => {
+                let pieces: Vec<&str> = text.split(',').collect();
+                if pieces.len() != 2 {
+                    return false;
+                }
+                let dx = match pieces[0].trim().parse::<i32>() {
+                    Ok(dx) => dx,
+                    Err(_) => return false,
+                };
+                let dy = match pieces[1].trim().parse::<i32>() {
+                    Ok(dy) => dy,
+                    Err(_) => return false,
+                };
+                let all = mode == Mode::OffsetAll;
+                state.mutation().offset_images(dx, dy, all);
+                true
+            }
+            Mode::OnionSkin => match text.trim().parse::<u32>() {
+                Ok(frames) => {
+                    state.set_onion_skin(frames);
+                    true
+                }
+                Err(_) => false,
+            },
+            Mode::Reference => {
+                if text.ends_with("/") || text.trim().is_empty() {
+                    state.set_reference(None);
+                    return true;
+                }
+                match Reference::load(&text) {
+                    Ok(reference) => {
+                        state.set_reference(Some(reference));
+                        true
+                    }
+                    Err(error) => {
+                        println!("Error loading reference PNG: {}", error);
+                        false
+                    }
+                }
+            }
+            Mode::ReferenceSettings => match state.reference() {
+                Some(reference) => {
+                    let mut reference = reference.clone();
+                    if reference.parse_settings(&text) {
+                        state.set_reference(Some(reference));
+                        true
+                    } else {
+                        false
+                    }
+                }
+                None => false,
+            },
+            Mode::Resize | Mode::ResizeCurrent => {
+                let (text, fill) = match text.find('#') {
+                    Some(index) => {
+                        match u32::from_str_radix(&text[(index + 1)..], 16) {
+                            Ok(value) if value < 16 => (
+                                &text[..index],
+                                util::color_at_index(value as usize),
+                            ),
+                            _ => return false,
+                        }
+                    }
+                    None => (text.as_str(), Color::C0),
+                };
+                let (text, anchor) = match text.find('@') {
+                    Some(index) => match Anchor::parse(&text[(index + 1)..]) {
+                        Some(anchor) => (&text[..index], anchor),
+                        None => return false,
+                    },
+                    None => (text, Anchor::TOP_LEFT),
+                };
                 let pieces: Vec<&str> = text.split('x').collect();
                 if pieces.len() != 2 {
                     return false;
@@ -322,9 +640,19 @@ impl EditorView {
                     Ok(height) => height,
                     Err(_) => return false,
                 };
-                state.mutation().resize_images(new_width, new_height);
+                let all = mode == Mode::Resize;
+                state
+                    .mutation()
+                    .resize_images(new_width, new_height, anchor, fill, all);
                 true
             }
+            Mode::Rotate => match text.trim().parse::<f64>() {
+                Ok(degrees) if degrees.is_finite() => {
+                    state.mutation().rotate_selection_by(degrees);
+                    true
+                }
+                _ => false,
+            },
             Mode::SaveAs => {
                 let old = state.swap_filepath(text);
                 match state.save_to_file() {
@@ -335,63 +663,51 @@ impl EditorView {
                     }
                 }
             }
-            Mode::SetColor(color) => {
-                let rgba = match (text.len(), u32::from_str_radix(&text, 16)) {
-                    (0, _) => (0, 0, 0, 0),
-                    (1, Ok(v)) => {
-                        let gray = (0x11 * v) as u8;
-                        (gray, gray, gray, 255)
-                    }
-                    (2, Ok(v)) => {
-                        let gray = v as u8;
-                        (gray, gray, gray, 255)
-                    }
-                    (3, Ok(v)) => {
-                        let r = (0x11 * (0xf & (v >> 8))) as u8;
-                        let g = (0x11 * (0xf & (v >> 4))) as u8;
-                        let b = (0x11 * (0xf & v)) as u8;
-                        (r, g, b, 255)
-                    }
-                    (4, Ok(v)) => {
-                        let r = (0x11 * (0xf & (v >> 12))) as u8;
-                        let g = (0x11 * (0xf & (v >> 8))) as u8;
-                        let b = (0x11 * (0xf & (v >> 4))) as u8;
-                        let a = (0x11 * (0xf & v)) as u8;
-                        (r, g, b, a)
-                    }
-                    (6, Ok(v)) => {
-                        let r = (0xff & (v >> 16)) as u8;
-                        let g = (0xff & (v >> 8)) as u8;
-                        let b = (0xff & v) as u8;
-                        (r, g, b, 255)
-                    }
-                    (8, Ok(v)) => {
-                        let r = (0xff & (v >> 24)) as u8;
-                        let g = (0xff & (v >> 16)) as u8;
-                        let b = (0xff & (v >> 8)) as u8;
-                        let a = (0xff & v) as u8;
-                        (r, g, b, a)
-                    }
-                    _ => return false,
+            Mode::ScaleTo => {
+                let mut words = text.split_whitespace();
+                let pieces: Vec<&str> = match words.next() {
+                    Some(size) => size.split('x').collect(),
+                    None => return false,
                 };
-                state.mutation().set_palette_color(color, rgba)
-            }
-            Mode::SetGrid => {
-                let pieces: Vec<&str> = text.split('x').collect();
-                if pieces.len() != 2 {
+                let method = match words.next() {
+                    Some(name) => match Resample::parse(name) {
+                        Some(method) => method,
+                        None => return false,
+                    },
+                    None => Resample::Nearest,
+                };
+                if pieces.len() != 2 || words.next().is_some() {
                     return false;
                 }
-                let new_horz = match pieces[0].parse::<u32>() {
-                    Ok(horz) => horz,
-                    Err(_) => return false,
+                let width = match pieces[0].parse::<u32>() {
+                    Ok(width) if width > 0 => width,
+                    _ => return false,
                 };
-                let new_vert = match pieces[1].parse::<u32>() {
-                    Ok(vert) => vert,
-                    Err(_) => return false,
+                let height = match pieces[1].parse::<u32>() {
+                    Ok(height) if height > 0 => height,
+                    _ => return false,
                 };
-                state.set_grid(new_horz, new_vert);
+                state.mutation().scale_selection_to(width, height, method);
                 true
             }
+            Mode::SetColor(color) => match util::parse_rgba_hex(&text) {
+                Some(rgba) => state.mutation().set_palette_color(color, rgba),
+                None => false,
+            },
+            Mode::SetDither => match Dither::parse(&text) {
+                Some(dither) => {
+                    state.set_dither(dither);
+                    true
+                }
+                None => false,
+            },
+            Mode::SetGrid => match Grid::parse(&text) {
+                Some(grid) => {
+                    state.set_grid(grid);
+                    true
+                }
+                None => false,
+            },
             Mode::SetMetadata => {
                 let result = if text.is_empty() {
                     Ok(vec![])
@@ -430,21 +746,67 @@ impl EditorView {
                 );
                 true
             }
+            Mode::SetRamps => {
+                let result = text
+                    .split_whitespace()
+                    .map(sidecar::parse_ramp)
+                    .collect::<Option<Vec<_>>>();
+                match result {
+                    Some(ramps) => {
+                        state.mutation().set_ramps(ramps);
+                        true
+                    }
+                    None => false,
+                }
+            }
             Mode::SetTag => {
                 state.mutation().set_tag(text);
                 true
             }
+            Mode::ShearHorz | Mode::ShearVert => match Shear::parse(&text) {
+                Some(shear) => {
+                    let vertical = mode == Mode::ShearVert;
+                    state.mutation().shear_selection(shear, vertical);
+                    true
+                }
+                None => false,
+            },
             Mode::TestSentence => {
                 state.set_test_sentence(text);
                 true
             }
+            Mode::Trim | Mode::TrimAll => {
+                // The text names the two metadata slots that should receive
+                // the trimmed-off left/top offsets, or is empty to not record
+                // them at all.
+                let offset_slots = if text.trim().is_empty() {
+                    None
+                } else {
+                    let pieces: Vec<&str> = text.split(',').collect();
+                    if pieces.len() != 2 {
+                        return false;
+                    }
+                    let x_slot = match pieces[0].trim().parse::<usize>() {
+                        Ok(slot) if slot < MAX_METADATA_SLOT => slot,
+                        _ => return false,
+                    };
+                    let y_slot = match pieces[1].trim().parse::<usize>() {
+                        Ok(slot) if slot < MAX_METADATA_SLOT => slot,
+                        _ => return false,
+                    };
+                    Some((x_slot, y_slot))
+                };
+                let all = mode == Mode::TrimAll;
+                state.mutation().trim_images(all, offset_slots);
+                true
+            }
         }
     }
 
     fn chop_col_major(&mut self, state: &mut EditorState) -> bool {
-        let (grid_width, grid_height) = state.grid();
-        let chop_width = if grid_width == 0 { 8 } else { grid_width };
-        let chop_height = if grid_height == 0 { 8 } else { grid_height };
+        let grid = state.grid();
+        let chop_width = if grid.width == 0 { 8 } else { grid.width };
+        let chop_height = if grid.height == 0 { 8 } else { grid.height };
         let chop_cols = state.image().width() / chop_width;
         let chop_rows = state.image().height() / chop_height;
         let mut chopped = Vec::<ahi::Image>::new();
@@ -461,9 +823,9 @@ impl EditorView {
     }
 
     fn chop_row_major(&mut self, state: &mut EditorState) -> bool {
-        let (grid_width, grid_height) = state.grid();
-        let chop_width = if grid_width == 0 { 8 } else { grid_width };
-        let chop_height = if grid_height == 0 { 8 } else { grid_height };
+        let grid = state.grid();
+        let chop_width = if grid.width == 0 { 8 } else { grid.width };
+        let chop_height = if grid.height == 0 { 8 } else { grid.height };
         let chop_cols = state.image().width() / chop_width;
         let chop_rows = state.image().height() / chop_height;
         let mut chopped = Vec::<ahi::Image>::new();
@@ -484,7 +846,19 @@ impl EditorView {
         state: &mut EditorState,
         menu_action: MenuAction,
     ) -> Action<()> {
+        if menu_action.edits_active_layer() && !state.active_layer_editable() {
+            return Action::ignore().and_stop();
+        }
         let action = match menu_action {
+            MenuAction::AddLayer => {
+                Action::redraw_if(state.mutation().add_layer())
+            }
+            MenuAction::AdjustReference => {
+                Action::redraw_if(self.begin_adjust_reference(state))
+            }
+            MenuAction::CaptureBrush => {
+                Action::redraw_if(state.capture_brush())
+            }
             MenuAction::ChopColMajor => {
                 self.chop_col_major(state);
                 Action::redraw()
@@ -493,6 +867,18 @@ impl EditorView {
                 self.chop_row_major(state);
                 Action::redraw()
             }
+            MenuAction::ClearBrush => Action::redraw_if(state.clear_brush()),
+            MenuAction::ClearGuides => {
+                let changed = !state.guides().is_empty();
+                state.set_guides(Vec::new());
+                Action::redraw_if(changed)
+            }
+            MenuAction::DeleteLayer => {
+                Action::redraw_if(state.mutation().delete_layer())
+            }
+            MenuAction::DropShadow => {
+                Action::redraw_if(self.begin_drop_shadow())
+            }
             MenuAction::ExportPng => {
                 Action::redraw_if(self.begin_export(state))
             }
@@ -504,10 +890,47 @@ impl EditorView {
                 state.mutation().flip_selection_vert();
                 Action::redraw()
             }
+            MenuAction::GenerateRamp => {
+                Action::redraw_if(self.begin_generate_ramp(state))
+            }
             MenuAction::ImportPng => {
                 Action::redraw_if(self.begin_import(state))
             }
-            MenuAction::Resize => Action::redraw_if(self.begin_resize(state)),
+            MenuAction::Italicize => {
+                Action::redraw_if(self.begin_italicize(state))
+            }
+            MenuAction::LoadReference => {
+                Action::redraw_if(self.begin_load_reference(state))
+            }
+            MenuAction::MoveLayerDown => {
+                Action::redraw_if(state.mutation().move_layer(false))
+            }
+            MenuAction::MoveLayerUp => {
+                Action::redraw_if(state.mutation().move_layer(true))
+            }
+            MenuAction::OffsetAll => {
+                Action::redraw_if(self.begin_offset(true))
+            }
+            MenuAction::OffsetImage => {
+                Action::redraw_if(self.begin_offset(false))
+            }
+            MenuAction::Outline4 => {
+                let color = state.color();
+                state.mutation().outline_selection(color, false);
+                Action::redraw()
+            }
+            MenuAction::Outline8 => {
+                let color = state.color();
+                state.mutation().outline_selection(color, true);
+                Action::redraw()
+            }
+            MenuAction::Resize => {
+                Action::redraw_if(self.begin_resize(state, true))
+            }
+            MenuAction::ResizeCurrent => {
+                Action::redraw_if(self.begin_resize(state, false))
+            }
+            MenuAction::RotateBy => Action::redraw_if(self.begin_rotate()),
             MenuAction::RotateLeft => {
                 state.mutation().rotate_selection_counterclockwise();
                 Action::redraw()
@@ -517,13 +940,70 @@ impl EditorView {
                 Action::redraw()
             }
             MenuAction::Scale2x => {
-                state.mutation().scale_selection_up(2);
+                state.mutation().scale_selection_up(Upscale::Nearest(2));
+                Action::redraw()
+            }
+            MenuAction::Scale3x => {
+                state.mutation().scale_selection_up(Upscale::Scale3x);
+                Action::redraw()
+            }
+            MenuAction::ScaleEpx => {
+                state.mutation().scale_selection_up(Upscale::Epx);
                 Action::redraw()
             }
             MenuAction::ScaleHalf => {
                 state.mutation().scale_selection_down(2);
                 Action::redraw()
             }
+            MenuAction::ScaleTo => {
+                Action::redraw_if(self.begin_scale_to(state))
+            }
+            MenuAction::ScaleXbr => {
+                state.mutation().scale_selection_up(Upscale::Xbr);
+                Action::redraw()
+            }
+            MenuAction::SetDither => {
+                Action::redraw_if(self.begin_set_dither(state))
+            }
+            MenuAction::SetMirrorAxes => {
+                Action::redraw_if(self.begin_set_mirror_axes(state))
+            }
+            MenuAction::SetOnionSkin => {
+                Action::redraw_if(self.begin_set_onion_skin(state))
+            }
+            MenuAction::SetRamps => {
+                Action::redraw_if(self.begin_set_ramps(state))
+            }
+            MenuAction::ShearHorz => {
+                Action::redraw_if(self.begin_shear(false))
+            }
+            MenuAction::ShearVert => Action::redraw_if(self.begin_shear(true)),
+            MenuAction::TogglePixelPerfect => {
+                let pixel_perfect = !state.pixel_perfect();
+                state.set_pixel_perfect(pixel_perfect);
+                Action::redraw()
+            }
+            MenuAction::ToggleCheckerboard => {
+                let checkerboard = !state.checkerboard();
+                state.set_checkerboard(checkerboard);
+                Action::redraw()
+            }
+            MenuAction::ToggleIndexView => {
+                let index_view = !state.index_view();
+                state.set_index_view(index_view);
+                Action::redraw()
+            }
+            MenuAction::ToggleSeamless => {
+                let seamless = !state.seamless();
+                state.set_seamless(seamless);
+                Action::redraw()
+            }
+            MenuAction::Trim => {
+                Action::redraw_if(self.begin_trim(state, false))
+            }
+            MenuAction::TrimAll => {
+                Action::redraw_if(self.begin_trim(state, true))
+            }
         };
         action.and_stop()
     }
@@ -558,6 +1038,9 @@ impl GuiElement<EditorState, ()> for EditorView {
                 state.mutation().select_all();
                 Action::redraw().and_stop()
             }
+            &Event::KeyDown(Keycode::B, kmod) if kmod == COMMAND => {
+                self.perform(state, MenuAction::CaptureBrush)
+            }
             &Event::KeyDown(Keycode::B, kmod) if kmod == COMMAND | SHIFT => {
                 Action::redraw_if(self.begin_set_metrics(state)).and_stop()
             }
@@ -565,6 +1048,9 @@ impl GuiElement<EditorState, ()> for EditorView {
                 state.mutation().copy_selection();
                 Action::ignore().and_stop()
             }
+            &Event::KeyDown(Keycode::D, kmod) if kmod == COMMAND => {
+                self.perform(state, MenuAction::SetDither)
+            }
             &Event::KeyDown(Keycode::G, kmod) if kmod == COMMAND => {
                 Action::redraw_if(self.begin_goto(state)).and_stop()
             }
@@ -574,6 +1060,9 @@ impl GuiElement<EditorState, ()> for EditorView {
             &Event::KeyDown(Keycode::H, kmod) if kmod == COMMAND | SHIFT => {
                 self.perform(state, MenuAction::FlipHorz)
             }
+            &Event::KeyDown(Keycode::I, kmod) if kmod == COMMAND => {
+                self.perform(state, MenuAction::ToggleIndexView)
+            }
             &Event::KeyDown(Keycode::L, kmod) if kmod == COMMAND | SHIFT => {
                 self.perform(state, MenuAction::RotateLeft)
             }
@@ -583,9 +1072,18 @@ impl GuiElement<EditorState, ()> for EditorView {
             &Event::KeyDown(Keycode::N, kmod) if kmod == COMMAND => {
                 Action::redraw_if(self.begin_new_image(state)).and_stop()
             }
+            &Event::KeyDown(Keycode::N, kmod) if kmod == COMMAND | SHIFT => {
+                self.perform(state, MenuAction::AddLayer)
+            }
             &Event::KeyDown(Keycode::O, kmod) if kmod == COMMAND => {
                 Action::redraw_if(self.begin_load_file(state)).and_stop()
             }
+            &Event::KeyDown(Keycode::O, kmod) if kmod == COMMAND | SHIFT => {
+                self.perform(state, MenuAction::SetOnionSkin)
+            }
+            &Event::KeyDown(Keycode::P, kmod) if kmod == COMMAND => {
+                self.perform(state, MenuAction::TogglePixelPerfect)
+            }
             &Event::KeyDown(Keycode::R, kmod) if kmod == COMMAND => {
                 self.perform(state, MenuAction::Resize)
             }
@@ -607,6 +1105,9 @@ impl GuiElement<EditorState, ()> for EditorView {
                     .and_stop()
             }
             &Event::KeyDown(Keycode::V, kmod) if kmod == COMMAND => {
+                if !state.active_layer_editable() {
+                    return Action::ignore().and_stop();
+                }
                 state.mutation().paste_selection();
                 Action::redraw().and_stop()
             }
@@ -614,6 +1115,9 @@ impl GuiElement<EditorState, ()> for EditorView {
                 self.perform(state, MenuAction::FlipVert)
             }
             &Event::KeyDown(Keycode::X, kmod) if kmod == COMMAND => {
+                if !state.active_layer_editable() {
+                    return Action::ignore().and_stop();
+                }
                 state.mutation().cut_selection();
                 Action::redraw().and_stop()
             }
@@ -623,12 +1127,48 @@ impl GuiElement<EditorState, ()> for EditorView {
             &Event::KeyDown(Keycode::Z, kmod) if kmod == COMMAND | SHIFT => {
                 Action::redraw_if(state.redo()).and_stop()
             }
+            &Event::KeyDown(Keycode::LeftBracket, kmod) if kmod == COMMAND => {
+                self.perform(state, MenuAction::MoveLayerDown)
+            }
+            &Event::KeyDown(Keycode::RightBracket, kmod)
+                if kmod == COMMAND =>
+            {
+                self.perform(state, MenuAction::MoveLayerUp)
+            }
             &Event::KeyDown(Keycode::Num2, kmod) if kmod == COMMAND => {
-                state.mutation().scale_selection_up(2);
-                Action::redraw().and_stop()
+                self.perform(state, MenuAction::Scale2x)
+            }
+            &Event::KeyDown(Keycode::Num2, kmod)
+                if kmod == COMMAND | SHIFT =>
+            {
+                self.perform(state, MenuAction::ScaleEpx)
+            }
+            &Event::KeyDown(Keycode::Num2, kmod) if kmod == COMMAND | ALT => {
+                self.perform(state, MenuAction::ScaleXbr)
             }
             &Event::KeyDown(Keycode::Num3, kmod) if kmod == COMMAND => {
-                state.mutation().scale_selection_up(3);
+                if !state.active_layer_editable() {
+                    return Action::ignore().and_stop();
+                }
+                state.mutation().scale_selection_up(Upscale::Nearest(3));
+                Action::redraw().and_stop()
+            }
+            &Event::KeyDown(Keycode::Num3, kmod)
+                if kmod == COMMAND | SHIFT =>
+            {
+                self.perform(state, MenuAction::Scale3x)
+            }
+            &Event::KeyDown(key, kmod)
+                if (kmod == ALT || kmod == ALT | SHIFT)
+                    && self.textbox.mode() == Mode::Edit
+                    && arrow_key_delta(key).is_some() =>
+            {
+                let (dx, dy) = arrow_key_delta(key).unwrap();
+                let all = kmod.contains(SHIFT);
+                if !all && !state.active_layer_editable() {
+                    return Action::ignore().and_stop();
+                }
+                state.mutation().offset_images(dx, dy, all);
                 Action::redraw().and_stop()
             }
             _ => {
@@ -637,6 +1177,7 @@ impl GuiElement<EditorState, ()> for EditorView {
                     let mut subaction = self.textbox.on_event(event, state);
                     if let Some((mode, text)) = subaction.take_value() {
                         if self.finish_mode(state, mode, text) {
+                            state.set_color_preview(None);
                             self.textbox.clear_mode();
                             subaction.also_redraw();
                         }
@@ -674,3 +1215,38 @@ impl GuiElement<EditorState, ()> for EditorView {
 }
 
 //===========================================================================//
+
+fn parse_ramp_endpoints(text: &str) -> Option<(Color, Color, f64)> {
+    let mut pieces = text.split_whitespace();
+    let mut slots = pieces.next()?.splitn(2, '-');
+    let mut parse_slot = || {
+        let index = u32::from_str_radix(slots.next()?, 16).ok()?;
+        if index < 16 {
+            Some(util::color_at_index(index as usize))
+        } else {
+            None
+        }
+    };
+    let start = parse_slot()?;
+    let end = parse_slot()?;
+    let hue_shift = match pieces.next() {
+        Some(piece) => piece.parse::<f64>().ok().filter(|v| v.is_finite())?,
+        None => 0.0,
+    };
+    if pieces.next().is_some() {
+        return None;
+    }
+    Some((start, end, hue_shift))
+}
+
+fn arrow_key_delta(key: Keycode) -> Option<(i32, i32)> {
+    match key {
+        Keycode::Left => Some((-1, 0)),
+        Keycode::Right => Some((1, 0)),
+        Keycode::Up => Some((0, -1)),
+        Keycode::Down => Some((0, 1)),
+        _ => None,
+    }
+}
+
+//===========================================================================//
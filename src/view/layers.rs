@@ -0,0 +1,154 @@
+// +--------------------------------------------------------------------------+
+// | Copyright 2016 Matthew D. Steele <mdsteele@alum.mit.edu>                 |
+// |                                                                          |
+// | This file is part of Tuna.                                               |
+// |                                                                          |
+// | Tuna is free software: you can redistribute it and/or modify it under    |
+// | the terms of the GNU General Public License as published by the Free     |
+// | Software Foundation, either version 3 of the License, or (at your        |
+// | option) any later version.                                               |
+// |                                                                          |
+// | Tuna is distributed in the hope that it will be useful, but WITHOUT ANY  |
+// | WARRANTY; without even the implied warranty of MERCHANTABILITY or        |
+// | FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License    |
+// | for details.                                                             |
+// |                                                                          |
+// | You should have received a copy of the GNU General Public License along  |
+// | with Tuna.  If not, see <http://www.gnu.org/licenses/>.                  |
+// +--------------------------------------------------------------------------+
+
+use crate::canvas::{Canvas, Resources};
+use crate::element::{Action, GuiElement};
+use crate::event::Event;
+use crate::state::EditorState;
+use sdl2::rect::{Point, Rect};
+use std::cmp;
+
+//===========================================================================//
+
+const ROW_HEIGHT: i32 = 16;
+const EYE_LEFT: i32 = 2;
+const LOCK_LEFT: i32 = 16;
+const LABEL_LEFT: i32 = 32;
+const BOX_SIZE: u32 = 12;
+
+//===========================================================================//
+
+pub struct LayersView {
+    rect: Rect,
+}
+
+impl LayersView {
+    pub fn new(left: i32, top: i32, width: u32, height: u32) -> LayersView {
+        LayersView { rect: Rect::new(left, top, width, height) }
+    }
+
+    fn max_rows(&self) -> usize {
+        (self.rect.height() as usize) / (ROW_HEIGHT as usize)
+    }
+
+    // Returns the layer indices to show, from the top row down.  Higher
+    // layers are drawn on top, so they are listed first.
+    fn row_indices(&self, state: &EditorState) -> Vec<usize> {
+        let (num_layers, active) = layer_count(state);
+        let max_rows = self.max_rows();
+        let top = cmp::max(active + 1, cmp::min(num_layers, max_rows));
+        (top.saturating_sub(max_rows)..top).rev().collect()
+    }
+}
+
+impl GuiElement<EditorState, ()> for LayersView {
+    fn draw(
+        &self,
+        state: &EditorState,
+        resources: &Resources,
+        canvas: &mut Canvas,
+    ) {
+        if state.font().is_some() {
+            return;
+        }
+        let mut canvas = canvas.subcanvas(self.rect);
+        canvas.clear((95, 95, 95, 255));
+        let (_, active) = layer_count(state);
+        for (row, index) in self.row_indices(state).into_iter().enumerate() {
+            let top = (row as i32) * ROW_HEIGHT;
+            if index == active {
+                let rect =
+                    Rect::new(0, top, self.rect.width(), ROW_HEIGHT as u32);
+                canvas.fill_rect((127, 127, 63, 255), rect);
+            }
+            let (visible, locked) = match state.layers() {
+                Some(stack) => {
+                    (stack.is_visible(index), stack.is_locked(index))
+                }
+                None => (true, false),
+            };
+            let eye = Rect::new(EYE_LEFT, top + 2, BOX_SIZE, BOX_SIZE);
+            canvas.draw_rect((255, 255, 255, 255), eye);
+            if visible {
+                canvas.fill_rect((255, 255, 255, 255), inner_rect(eye));
+            }
+            let lock = Rect::new(LOCK_LEFT, top + 2, BOX_SIZE, BOX_SIZE);
+            canvas.draw_rect((255, 255, 255, 255), lock);
+            if locked {
+                canvas.fill_rect((255, 128, 0, 255), inner_rect(lock));
+            }
+            canvas.draw_string(
+                resources.font(),
+                LABEL_LEFT,
+                top + 2,
+                &format!("Layer {}", index + 1),
+            );
+        }
+    }
+
+    fn on_event(
+        &mut self,
+        event: &Event,
+        state: &mut EditorState,
+    ) -> Action<()> {
+        match event {
+            &Event::MouseDown(pt, _) => {
+                if !self.rect.contains_point(pt) || state.font().is_some() {
+                    return Action::ignore();
+                }
+                let pt = pt - self.rect.top_left();
+                let row = (pt.y() / ROW_HEIGHT) as usize;
+                let index = match self.row_indices(state).get(row) {
+                    Some(&index) => index,
+                    None => return Action::ignore().and_stop(),
+                };
+                let changed = if box_contains(EYE_LEFT, pt) {
+                    state.mutation().toggle_layer_visible(index)
+                } else if box_contains(LOCK_LEFT, pt) {
+                    state.mutation().toggle_layer_locked(index)
+                } else if index != layer_count(state).1 {
+                    state.mutation().select_layer(index)
+                } else {
+                    false
+                };
+                Action::redraw_if(changed).and_stop()
+            }
+            _ => Action::ignore(),
+        }
+    }
+}
+
+//===========================================================================//
+
+fn layer_count(state: &EditorState) -> (usize, usize) {
+    match state.layers() {
+        Some(stack) => (stack.len(), stack.active()),
+        None => (1, 0),
+    }
+}
+
+fn box_contains(left: i32, pt: Point) -> bool {
+    pt.x() >= left && pt.x() < left + BOX_SIZE as i32
+}
+
+fn inner_rect(rect: Rect) -> Rect {
+    Rect::new(rect.x() + 3, rect.y() + 3, rect.width() - 6, rect.height() - 6)
+}
+
+//===========================================================================//
@@ -27,61 +27,202 @@ use sdl2::rect::Rect;
 
 #[derive(Clone, Copy)]
 pub enum MenuAction {
+    AddLayer,
+    AdjustReference,
+    CaptureBrush,
     ChopColMajor,
     ChopRowMajor,
+    ClearBrush,
+    ClearGuides,
+    DeleteLayer,
+    DropShadow,
     ExportPng,
     FlipHorz,
     FlipVert,
+    GenerateRamp,
     ImportPng,
+    Italicize,
+    LoadReference,
+    MoveLayerDown,
+    MoveLayerUp,
+    OffsetAll,
+    OffsetImage,
+    Outline4,
+    Outline8,
     Resize,
+    ResizeCurrent,
+    RotateBy,
     RotateLeft,
     RotateRight,
     Scale2x,
+    Scale3x,
+    ScaleEpx,
     ScaleHalf,
+    ScaleTo,
+    ScaleXbr,
+    SetDither,
+    SetMirrorAxes,
+    SetOnionSkin,
+    SetRamps,
+    ShearHorz,
+    ShearVert,
+    ToggleCheckerboard,
+    ToggleIndexView,
+    TogglePixelPerfect,
+    ToggleSeamless,
+    Trim,
+    TrimAll,
 }
 
 impl MenuAction {
     pub fn label(&self) -> &'static str {
         match *self {
+            MenuAction::AddLayer => "Add layer",
+            MenuAction::AdjustReference => "Adjust reference",
+            MenuAction::CaptureBrush => "Capture brush",
             MenuAction::ChopColMajor => "Chop col-major",
             MenuAction::ChopRowMajor => "Chop row-major",
+            MenuAction::ClearBrush => "Clear brush",
+            MenuAction::ClearGuides => "Clear guides",
+            MenuAction::DeleteLayer => "Delete layer",
+            MenuAction::DropShadow => "Drop shadow",
             MenuAction::ExportPng => "Export PNG",
             MenuAction::FlipHorz => "Flip horizontally",
             MenuAction::FlipVert => "Flip vertically",
+            MenuAction::GenerateRamp => "Generate color ramp",
             MenuAction::ImportPng => "Import PNG",
+            MenuAction::Italicize => "Italicize font",
+            MenuAction::LoadReference => "Load reference PNG",
+            MenuAction::MoveLayerDown => "Move layer down",
+            MenuAction::MoveLayerUp => "Move layer up",
+            MenuAction::OffsetAll => "Offset all images",
+            MenuAction::OffsetImage => "Offset image",
+            MenuAction::Outline4 => "Outline (4-way)",
+            MenuAction::Outline8 => "Outline (8-way)",
             MenuAction::Resize => "Resize images",
+            MenuAction::ResizeCurrent => "Resize current image",
+            MenuAction::RotateBy => "Rotate by angle",
             MenuAction::RotateLeft => "Rotate left (CCW)",
             MenuAction::RotateRight => "Rotate right (CW)",
             MenuAction::Scale2x => "Scale 2x",
+            MenuAction::Scale3x => "Scale 3x (Scale3x)",
+            MenuAction::ScaleEpx => "Scale 2x (EPX)",
             MenuAction::ScaleHalf => "Scale 1/2",
+            MenuAction::ScaleTo => "Scale to size",
+            MenuAction::ScaleXbr => "Scale 2x (xBR)",
+            MenuAction::SetDither => "Set dither level",
+            MenuAction::SetMirrorAxes => "Set mirror axes",
+            MenuAction::SetOnionSkin => "Onion skin frames",
+            MenuAction::SetRamps => "Set color ramps",
+            MenuAction::ShearHorz => "Shear horizontally",
+            MenuAction::ShearVert => "Shear vertically",
+            MenuAction::ToggleCheckerboard => "Checkerboard background",
+            MenuAction::ToggleIndexView => "Color index view",
+            MenuAction::TogglePixelPerfect => "Pixel-perfect pencil",
+            MenuAction::ToggleSeamless => "Seamless (wrap) mode",
+            MenuAction::Trim => "Trim image",
+            MenuAction::TrimAll => "Trim all images",
         }
     }
 
     pub fn shortcut(&self) -> &'static str {
         match *self {
+            MenuAction::AddLayer => "CS-N",
+            MenuAction::CaptureBrush => "C-B",
             MenuAction::FlipHorz => "CS-H",
             MenuAction::FlipVert => "CS-V",
+            MenuAction::MoveLayerDown => "C-[",
+            MenuAction::MoveLayerUp => "C-]",
             MenuAction::Resize => "C-R",
             MenuAction::RotateLeft => "CS-L",
             MenuAction::RotateRight => "CS-R",
             MenuAction::Scale2x => "C-2",
+            MenuAction::Scale3x => "CS-3",
+            MenuAction::ScaleEpx => "CS-2",
+            MenuAction::ScaleXbr => "CA-2",
+            MenuAction::SetDither => "C-D",
+            MenuAction::SetOnionSkin => "CS-O",
+            MenuAction::ToggleIndexView => "C-I",
+            MenuAction::TogglePixelPerfect => "C-P",
             _ => "",
         }
     }
 
+    /// Returns true if this action changes the pixels of the current image's
+    /// active layer, and so must be refused while that layer is locked or
+    /// hidden.
+    pub fn edits_active_layer(&self) -> bool {
+        matches!(
+            *self,
+            MenuAction::DropShadow
+                | MenuAction::FlipHorz
+                | MenuAction::FlipVert
+                | MenuAction::OffsetImage
+                | MenuAction::Outline4
+                | MenuAction::Outline8
+                | MenuAction::ResizeCurrent
+                | MenuAction::RotateBy
+                | MenuAction::RotateLeft
+                | MenuAction::RotateRight
+                | MenuAction::Scale2x
+                | MenuAction::Scale3x
+                | MenuAction::ScaleEpx
+                | MenuAction::ScaleHalf
+                | MenuAction::ScaleTo
+                | MenuAction::ScaleXbr
+                | MenuAction::ShearHorz
+                | MenuAction::ShearVert
+                | MenuAction::Trim
+        )
+    }
+
     pub fn all() -> Vec<MenuAction> {
         vec![
+            MenuAction::AddLayer,
+            MenuAction::AdjustReference,
+            MenuAction::CaptureBrush,
             MenuAction::ChopColMajor,
             MenuAction::ChopRowMajor,
+            MenuAction::ClearBrush,
+            MenuAction::ClearGuides,
+            MenuAction::DeleteLayer,
+            MenuAction::DropShadow,
             MenuAction::ExportPng,
             MenuAction::FlipHorz,
             MenuAction::FlipVert,
+            MenuAction::GenerateRamp,
             MenuAction::ImportPng,
+            MenuAction::Italicize,
+            MenuAction::LoadReference,
+            MenuAction::MoveLayerDown,
+            MenuAction::MoveLayerUp,
+            MenuAction::OffsetAll,
+            MenuAction::OffsetImage,
+            MenuAction::Outline4,
+            MenuAction::Outline8,
             MenuAction::Resize,
+            MenuAction::ResizeCurrent,
+            MenuAction::RotateBy,
             MenuAction::RotateLeft,
             MenuAction::RotateRight,
             MenuAction::Scale2x,
+            MenuAction::Scale3x,
+            MenuAction::ScaleEpx,
             MenuAction::ScaleHalf,
+            MenuAction::ScaleTo,
+            MenuAction::ScaleXbr,
+            MenuAction::SetDither,
+            MenuAction::SetMirrorAxes,
+            MenuAction::SetOnionSkin,
+            MenuAction::SetRamps,
+            MenuAction::ShearHorz,
+            MenuAction::ShearVert,
+            MenuAction::ToggleCheckerboard,
+            MenuAction::ToggleIndexView,
+            MenuAction::TogglePixelPerfect,
+            MenuAction::ToggleSeamless,
+            MenuAction::Trim,
+            MenuAction::TrimAll,
         ]
     }
 }
@@ -178,7 +319,7 @@ impl GuiElement<(), ()> for MenuButton {
 
     fn on_event(&mut self, event: &Event, _: &mut ()) -> Action<()> {
         match event {
-            &Event::MouseDown(_) => Action::ignore().and_return(()),
+            &Event::MouseDown(_, _) => Action::ignore().and_return(()),
             _ => Action::ignore(),
         }
     }
@@ -196,29 +337,34 @@ impl MenuItems {
     const WIDTH: u32 = 200;
     const ITEM_WIDTH: u32 = MenuItems::WIDTH - MenuItems::MARGIN * 2;
     const ITEM_HEIGHT: u32 = 14;
+    const MAX_ROWS: usize = 24;
 
     fn new(left: i32, bottom: i32) -> MenuItems {
         let items = AggregateElement::new(
             MenuAction::all()
                 .into_iter()
                 .enumerate()
-                .map(|(row, action)| MenuItems::item(row, action))
+                .map(|(index, action)| MenuItems::item(index, action))
                 .collect(),
         );
-        let width = MenuItems::WIDTH;
+        let num_columns = items.len().div_ceil(MenuItems::MAX_ROWS);
+        let num_rows = items.len().min(MenuItems::MAX_ROWS);
+        let width = MenuItems::WIDTH * (num_columns as u32);
         let height =
-            MenuItems::MARGIN + MenuItems::ITEM_HEIGHT * (items.len() as u32);
+            MenuItems::MARGIN + MenuItems::ITEM_HEIGHT * (num_rows as u32);
         let top = bottom - (height as i32);
         let rect = Rect::new(left, top, width, height);
         MenuItems { items, rect }
     }
 
     fn item(
-        row: usize,
+        index: usize,
         action: MenuAction,
     ) -> Box<dyn GuiElement<(), MenuAction>> {
+        let column = index / MenuItems::MAX_ROWS;
+        let row = index % MenuItems::MAX_ROWS;
         let rect = Rect::new(
-            MenuItems::MARGIN as i32,
+            (MenuItems::MARGIN + MenuItems::WIDTH * (column as u32)) as i32,
             (MenuItems::MARGIN as i32)
                 + (MenuItems::ITEM_HEIGHT as i32) * (row as i32),
             MenuItems::ITEM_WIDTH,
@@ -247,7 +393,7 @@ impl GuiElement<(), MenuAction> for MenuItems {
         );
         if !action.should_stop() {
             match event {
-                &Event::MouseDrag(pt) | &Event::MouseDown(pt) => {
+                &Event::MouseDrag(pt, _) | &Event::MouseDown(pt, _) => {
                     if self.rect.contains_point(pt) {
                         action = action.and_stop();
                     }
@@ -283,7 +429,9 @@ impl GuiElement<(), MenuAction> for MenuItem {
 
     fn on_event(&mut self, event: &Event, _: &mut ()) -> Action<MenuAction> {
         match event {
-            &Event::MouseDown(_) => Action::ignore().and_return(self.action),
+            &Event::MouseDown(_, _) => {
+                Action::ignore().and_return(self.action)
+            }
             _ => Action::ignore(),
         }
     }
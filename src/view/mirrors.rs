@@ -31,7 +31,7 @@ pub struct Mirrors {
 
 impl Mirrors {
     const WIDTH: u32 = 72;
-    const HEIGHT: u32 = 48;
+    const HEIGHT: u32 = 72;
 
     pub fn new(left: i32, top: i32) -> Mirrors {
         let elements: Vec<Box<dyn GuiElement<Mirror, ()>>> = vec![
@@ -41,6 +41,9 @@ impl Mirrors {
             Mirrors::picker(2, 26, Mirror::Vert),
             Mirrors::picker(26, 26, Mirror::Both),
             Mirrors::picker(50, 26, Mirror::Rot4),
+            Mirrors::picker(2, 50, Mirror::Diag),
+            Mirrors::picker(26, 50, Mirror::AntiDiag),
+            Mirrors::picker(50, 50, Mirror::Kaleido),
         ];
         Mirrors {
             element: SubrectElement::new(
@@ -103,6 +106,9 @@ impl MirrorPicker {
             Mirror::Both => ToolIcon::MirrorBoth,
             Mirror::Rot2 => ToolIcon::MirrorRot2,
             Mirror::Rot4 => ToolIcon::MirrorRot4,
+            Mirror::Diag => ToolIcon::MirrorDiag,
+            Mirror::AntiDiag => ToolIcon::MirrorAntiDiag,
+            Mirror::Kaleido => ToolIcon::MirrorKaleido,
         };
         MirrorPicker { mirror, icon }
     }
@@ -125,7 +131,7 @@ impl GuiElement<Mirror, ()> for MirrorPicker {
 
     fn on_event(&mut self, event: &Event, mirror: &mut Mirror) -> Action<()> {
         match event {
-            &Event::MouseDown(_) => {
+            &Event::MouseDown(_, _) => {
                 *mirror = self.mirror;
                 return Action::redraw().and_stop();
             }
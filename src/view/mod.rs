@@ -18,10 +18,12 @@
 // +--------------------------------------------------------------------------+
 
 mod editor;
+mod layers;
 mod menu;
 mod metadata;
 mod mirrors;
 mod palette;
+mod rulers;
 mod scrollbar;
 mod textbox;
 mod tiles;
@@ -19,7 +19,7 @@
 
 use crate::canvas::{Canvas, Resources, ToolIcon};
 use crate::element::{Action, AggregateElement, GuiElement, SubrectElement};
-use crate::event::{Event, Keycode, NONE};
+use crate::event::{Event, Keycode, NONE, SHIFT};
 use crate::state::{EditorState, Tool};
 use ahi::{self, Color};
 use sdl2::rect::{Point, Rect};
@@ -244,6 +244,14 @@ impl ColorPicker {
             }
         }
     }
+
+    fn toggle_secondary_color(&self, state: &mut EditorState) {
+        if state.secondary_color() == Some(self.color) {
+            state.set_secondary_color(None);
+        } else {
+            state.set_secondary_color(Some(self.color));
+        }
+    }
 }
 
 impl GuiElement<EditorState, PaletteAction> for ColorPicker {
@@ -267,6 +275,11 @@ impl GuiElement<EditorState, PaletteAction> for ColorPicker {
         if state.color() == self.color {
             canvas.draw_rect((255, 255, 255, 255), rect);
         }
+        if state.secondary_color() == Some(self.color) {
+            let corner = Rect::new(rect.x(), rect.y(), 6, 6);
+            canvas.fill_rect((255, 255, 255, 255), corner);
+            canvas.draw_rect((0, 0, 0, 255), corner);
+        }
     }
 
     fn on_event(
@@ -280,7 +293,7 @@ impl GuiElement<EditorState, PaletteAction> for ColorPicker {
                     self.double_click_counter -= 1;
                 }
             }
-            &Event::MouseDown(_) => {
+            &Event::MouseDown(_, _) => {
                 if self.double_click_counter > 0 {
                     return Action::redraw()
                         .and_return(PaletteAction::EditColor(self.color));
@@ -294,6 +307,9 @@ impl GuiElement<EditorState, PaletteAction> for ColorPicker {
                 if key == self.key && kmod == NONE {
                     self.pick_color(state);
                     return Action::redraw().and_stop();
+                } else if key == self.key && kmod == SHIFT {
+                    self.toggle_secondary_color(state);
+                    return Action::redraw().and_stop();
                 }
             }
             _ => {}
@@ -348,7 +364,7 @@ impl GuiElement<EditorState, PaletteAction> for NextPrevPalette {
         state: &mut EditorState,
     ) -> Action<PaletteAction> {
         match event {
-            &Event::MouseDown(_) => {
+            &Event::MouseDown(_, _) => {
                 return self.increment(state);
             }
             &Event::KeyDown(key, kmod) => {
@@ -389,7 +405,7 @@ impl GuiElement<EditorState, PaletteAction> for AddPalettteButton {
         state: &mut EditorState,
     ) -> Action<PaletteAction> {
         match event {
-            &Event::MouseDown(_) => {
+            &Event::MouseDown(_, _) => {
                 state.mutation().add_new_palette();
                 Action::redraw().and_stop()
             }
@@ -427,7 +443,7 @@ impl GuiElement<EditorState, PaletteAction> for DeletePalettteButton {
         state: &mut EditorState,
     ) -> Action<PaletteAction> {
         match event {
-            &Event::MouseDown(_) => {
+            &Event::MouseDown(_, _) => {
                 state.mutation().delete_palette();
                 Action::redraw().and_stop()
             }
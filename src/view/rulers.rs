@@ -0,0 +1,276 @@
+// +--------------------------------------------------------------------------+
+// | Copyright 2016 Matthew D. Steele <mdsteele@alum.mit.edu>                 |
+// |                                                                          |
+// | This file is part of Tuna.                                               |
+// |                                                                          |
+// | Tuna is free software: you can redistribute it and/or modify it under    |
+// | the terms of the GNU General Public License as published by the Free     |
+// | Software Foundation, either version 3 of the License, or (at your        |
+// | option) any later version.                                               |
+// |                                                                          |
+// | Tuna is distributed in the hope that it will be useful, but WITHOUT ANY  |
+// | WARRANTY; without even the implied warranty of MERCHANTABILITY or        |
+// | FITNESS FOR A PARTICULAR PURPOSE.  See the GNU General Public License    |
+// | for details.                                                             |
+// |                                                                          |
+// | You should have received a copy of the GNU General Public License along  |
+// | with Tuna.  If not, see <http://www.gnu.org/licenses/>.                  |
+// +--------------------------------------------------------------------------+
+
+use crate::canvas::{Canvas, Resources};
+use crate::element::{Action, GuiElement};
+use crate::event::Event;
+use crate::grid::{Guide, GUIDE_COLOR};
+use crate::state::EditorState;
+use num_integer::div_floor;
+use sdl2::rect::{Point, Rect};
+use std::cmp;
+
+//===========================================================================//
+
+const TOP_RULER_HEIGHT: u32 = 12;
+const LEFT_RULER_WIDTH: u32 = 20;
+const RULER_GAP: i32 = 2;
+const RULER_COLOR: (u8, u8, u8, u8) = (160, 160, 160, 255);
+const TICK_COLOR: (u8, u8, u8, u8) = (32, 32, 32, 255);
+const MIN_TICK_SPACING: u32 = 4;
+const MIN_LABEL_SPACING: u32 = 32;
+const GRAB_DISTANCE: i32 = 3;
+
+//===========================================================================//
+
+struct GuideDrag {
+    vertical: bool,
+    index: usize,
+    outside: bool,
+}
+
+// Pixel rulers along the top and left edges of an image canvas.  Clicking a
+// ruler adds a guide (vertical guides on the top ruler, horizontal guides on
+// the left one) or grabs an existing one; dragging a guide off the end of its
+// ruler removes it.
+pub struct Rulers {
+    top_left: Point,
+    max_size: u32,
+    drag: Option<GuideDrag>,
+}
+
+impl Rulers {
+    pub fn new(left: i32, top: i32, max_size: u32) -> Rulers {
+        Rulers { top_left: Point::new(left, top), max_size, drag: None }
+    }
+
+    fn scale(&self, state: &EditorState) -> u32 {
+        let (width, height) = state.image_size();
+        cmp::max(1, self.max_size / cmp::max(width, height))
+    }
+
+    fn ruler_rect(&self, state: &EditorState, vertical: bool) -> Rect {
+        let scale = self.scale(state);
+        let (width, height) = state.image_size();
+        if vertical {
+            Rect::new(
+                self.top_left.x(),
+                self.top_left.y() - RULER_GAP - TOP_RULER_HEIGHT as i32,
+                width * scale + 1,
+                TOP_RULER_HEIGHT,
+            )
+        } else {
+            Rect::new(
+                self.top_left.x() - RULER_GAP - LEFT_RULER_WIDTH as i32,
+                self.top_left.y(),
+                LEFT_RULER_WIDTH,
+                height * scale + 1,
+            )
+        }
+    }
+
+    // Returns the pixel boundary nearest to the mouse along the given ruler
+    // (which may lie outside the image).
+    fn mouse_to_position(
+        &self,
+        mouse: Point,
+        vertical: bool,
+        state: &EditorState,
+    ) -> i32 {
+        let scale = self.scale(state) as i32;
+        let offset = if vertical {
+            mouse.x() - self.top_left.x()
+        } else {
+            mouse.y() - self.top_left.y()
+        };
+        div_floor(offset + scale / 2, scale)
+    }
+
+    fn draw_ruler(
+        &self,
+        state: &EditorState,
+        resources: &Resources,
+        canvas: &mut Canvas,
+        vertical: bool,
+    ) {
+        let scale = self.scale(state);
+        let (width, height) = state.image_size();
+        let extent = if vertical { width } else { height };
+        let mut canvas = canvas.subcanvas(self.ruler_rect(state, vertical));
+        canvas.clear(RULER_COLOR);
+        let tick_step = step_for_spacing(1, scale, MIN_TICK_SPACING);
+        let label_step = step_for_spacing(tick_step, scale, MIN_LABEL_SPACING);
+        let mut position = 0;
+        while position <= extent {
+            let offset = (position * scale) as i32;
+            let is_label = position % label_step == 0;
+            if vertical {
+                let length = if is_label { TOP_RULER_HEIGHT } else { 3 };
+                let top = (TOP_RULER_HEIGHT - length) as i32;
+                canvas
+                    .fill_rect(TICK_COLOR, Rect::new(offset, top, 1, length));
+            } else {
+                let length = if is_label { LEFT_RULER_WIDTH } else { 3 };
+                let left = (LEFT_RULER_WIDTH - length) as i32;
+                canvas
+                    .fill_rect(TICK_COLOR, Rect::new(left, offset, length, 1));
+            }
+            if is_label {
+                let label = format!("{}", position);
+                if vertical {
+                    canvas.draw_string(
+                        resources.font(),
+                        offset + 2,
+                        0,
+                        &label,
+                    );
+                } else {
+                    canvas.draw_string(resources.font(), 1, offset, &label);
+                }
+            }
+            position += tick_step;
+        }
+        for &guide in state.guides() {
+            match guide {
+                Guide::Vert(x) if vertical => {
+                    let x = (x * scale) as i32;
+                    canvas.fill_rect(
+                        GUIDE_COLOR,
+                        Rect::new(x - 1, 0, 3, TOP_RULER_HEIGHT),
+                    );
+                }
+                Guide::Horz(y) if !vertical => {
+                    let y = (y * scale) as i32;
+                    canvas.fill_rect(
+                        GUIDE_COLOR,
+                        Rect::new(0, y - 1, LEFT_RULER_WIDTH, 3),
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl GuiElement<EditorState, ()> for Rulers {
+    fn draw(
+        &self,
+        state: &EditorState,
+        resources: &Resources,
+        canvas: &mut Canvas,
+    ) {
+        self.draw_ruler(state, resources, canvas, true);
+        self.draw_ruler(state, resources, canvas, false);
+    }
+
+    fn on_event(
+        &mut self,
+        event: &Event,
+        state: &mut EditorState,
+    ) -> Action<()> {
+        match *event {
+            Event::MouseDown(pt, _) => {
+                let vertical =
+                    if self.ruler_rect(state, true).contains_point(pt) {
+                        true
+                    } else if self.ruler_rect(state, false).contains_point(pt)
+                    {
+                        false
+                    } else {
+                        return Action::ignore();
+                    };
+                let scale = self.scale(state) as i32;
+                let position = self.mouse_to_position(pt, vertical, state);
+                let offset = if vertical {
+                    pt.x() - self.top_left.x()
+                } else {
+                    pt.y() - self.top_left.y()
+                };
+                let mut guides = state.guides().to_vec();
+                // Grab an existing guide if one is close enough to the mouse,
+                // or else add a new one.
+                let grabbed = guides.iter().position(|&guide| match guide {
+                    Guide::Vert(x) if vertical => {
+                        (x as i32 * scale - offset).abs() <= GRAB_DISTANCE
+                    }
+                    Guide::Horz(y) if !vertical => {
+                        (y as i32 * scale - offset).abs() <= GRAB_DISTANCE
+                    }
+                    _ => false,
+                });
+                let index = match grabbed {
+                    Some(index) => index,
+                    None => {
+                        guides.push(Guide::new(vertical, position as u32));
+                        guides.len() - 1
+                    }
+                };
+                state.set_guides(guides);
+                self.drag =
+                    Some(GuideDrag { vertical, index, outside: false });
+                Action::redraw().and_stop()
+            }
+            Event::MouseDrag(pt, _) => {
+                let (vertical, index) = match self.drag {
+                    Some(ref drag) => (drag.vertical, drag.index),
+                    None => return Action::ignore(),
+                };
+                let (width, height) = state.image_size();
+                let extent = if vertical { width } else { height } as i32;
+                let position = self.mouse_to_position(pt, vertical, state);
+                let outside = position < 0 || position > extent;
+                self.drag = Some(GuideDrag { vertical, index, outside });
+                let position = cmp::max(0, cmp::min(position, extent));
+                let mut guides = state.guides().to_vec();
+                if index < guides.len() {
+                    guides[index] = Guide::new(vertical, position as u32);
+                    state.set_guides(guides);
+                }
+                Action::redraw().and_stop()
+            }
+            Event::MouseUp => {
+                if let Some(drag) = self.drag.take() {
+                    let mut guides = state.guides().to_vec();
+                    if drag.outside && drag.index < guides.len() {
+                        guides.remove(drag.index);
+                        state.set_guides(guides);
+                    }
+                    Action::redraw()
+                } else {
+                    Action::ignore()
+                }
+            }
+            _ => Action::ignore(),
+        }
+    }
+}
+
+//===========================================================================//
+
+// Returns the smallest power-of-two multiple of `base` whose on-screen size
+// (at the given scale) is at least `min_spacing` pixels.
+fn step_for_spacing(base: u32, scale: u32, min_spacing: u32) -> u32 {
+    let mut step = base;
+    while step * scale < min_spacing {
+        step *= 2;
+    }
+    step
+}
+
+//===========================================================================//
@@ -118,7 +118,13 @@ impl GuiElement<EditorState, ()> for ImagePicker {
         canvas: &mut Canvas,
     ) {
         let color = if let Some(index) = self.index(state) {
-            canvas.draw_image(state.image_at(index), state.palette(), 2, 2, 1);
+            canvas.draw_image(
+                &state.composite_image_at(index),
+                state.palette(),
+                2,
+                2,
+                1,
+            );
             if self.delta == 0 {
                 (255, 255, 127, 255)
             } else {
@@ -137,7 +143,7 @@ impl GuiElement<EditorState, ()> for ImagePicker {
         state: &mut EditorState,
     ) -> Action<()> {
         match event {
-            &Event::MouseDown(_) => {
+            &Event::MouseDown(_, _) => {
                 if let Some(index) = self.index(state) {
                     state.set_image_index(index);
                     Action::redraw().and_stop()
@@ -193,7 +199,7 @@ impl GuiElement<EditorState, ()> for NextPrevImage {
         state: &mut EditorState,
     ) -> Action<()> {
         match event {
-            &Event::MouseDown(_) => {
+            &Event::MouseDown(_, _) => {
                 return self.increment(state);
             }
             &Event::KeyDown(key, kmod) => {
@@ -21,6 +21,7 @@ use crate::canvas::{Canvas, Resources};
 use crate::element::{Action, AggregateElement, GuiElement, SubrectElement};
 use crate::event::{Event, Keycode, ALT};
 use crate::state::EditorState;
+use crate::util;
 use ahi::Color;
 use sdl2::rect::Rect;
 use std::cmp;
@@ -39,27 +40,45 @@ const LABEL_WIDTH: i32 = 50;
 
 #[derive(Clone, Copy, Eq, PartialEq)]
 pub enum Mode {
+    DropShadow,
     Edit,
     Export,
+    GenerateRamp,
     Goto,
     Import,
+    Italicize,
     LoadFile,
+    MirrorAxes,
     NewGlyph,
+    Offset,
+    OffsetAll,
+    OnionSkin,
+    Reference,
+    ReferenceSettings,
     Resize,
+    ResizeCurrent,
+    Rotate,
     SaveAs,
+    ScaleTo,
     SetColor(Color),
+    SetDither,
     SetGrid,
     SetMetadata,
     SetMetrics,
+    SetRamps,
     SetTag,
+    ShearHorz,
+    ShearVert,
     TestSentence,
+    Trim,
+    TrimAll,
 }
 
 impl Mode {
     fn tab_completion(self) -> Option<TabCompletion> {
         match self {
             Mode::Export | Mode::SaveAs => Some(TabCompletion::AllFiles),
-            Mode::Import => Some(TabCompletion::PngFiles),
+            Mode::Import | Mode::Reference => Some(TabCompletion::PngFiles),
             Mode::LoadFile => Some(TabCompletion::AhiFiles),
             _ => None,
         }
@@ -259,7 +278,7 @@ impl GuiElement<(), (u8, u8, u8, u8)> for RgbaSwatch {
         _: &mut (),
     ) -> Action<(u8, u8, u8, u8)> {
         match event {
-            &Event::MouseDown(_) => Action::redraw().and_return(self.rgba),
+            &Event::MouseDown(_, _) => Action::redraw().and_return(self.rgba),
             _ => Action::ignore(),
         }
     }
@@ -401,6 +420,198 @@ impl GuiElement<(), (u8, u8, u8, u8)> for RgbaPanel {
 
 //===========================================================================//
 
+const SLIDER_LABELS: &[&str] = &["R", "G", "B", "H", "S", "V", "A"];
+
+struct ColorSliders {
+    rgba: (u8, u8, u8, u8),
+    hsv: (f64, f64, f64),
+    dragging: Option<usize>,
+}
+
+impl ColorSliders {
+    const MARGIN: i32 = 6;
+    const ROW_HEIGHT: i32 = 14;
+    const LABEL_WIDTH: i32 = 14;
+    const VALUE_WIDTH: i32 = 30;
+    const WIDTH: u32 = RgbaPanel::WIDTH;
+    const HEIGHT: u32 = (ColorSliders::ROW_HEIGHT
+        * (SLIDER_LABELS.len() as i32)
+        + ColorSliders::MARGIN * 2) as u32;
+    const TRACK_LEFT: i32 = ColorSliders::MARGIN + ColorSliders::LABEL_WIDTH;
+    const TRACK_WIDTH: i32 = (ColorSliders::WIDTH as i32)
+        - ColorSliders::TRACK_LEFT
+        - ColorSliders::VALUE_WIDTH
+        - ColorSliders::MARGIN;
+
+    fn new() -> ColorSliders {
+        ColorSliders {
+            rgba: (0, 0, 0, 0),
+            hsv: (0.0, 0.0, 0.0),
+            dragging: None,
+        }
+    }
+
+    fn set_rgba(&mut self, rgba: (u8, u8, u8, u8)) {
+        let (hue, sat, val) = util::rgb_to_hsv((rgba.0, rgba.1, rgba.2));
+        // Keep the old hue (and saturation) when the new color doesn't
+        // determine them, so that dragging through gray or black doesn't
+        // snap the hue slider back to red.
+        self.hsv = if val == 0.0 {
+            (self.hsv.0, self.hsv.1, val)
+        } else if sat == 0.0 {
+            (self.hsv.0, sat, val)
+        } else {
+            (hue, sat, val)
+        };
+        self.rgba = rgba;
+    }
+
+    fn fraction(&self, slider: usize) -> f64 {
+        match slider {
+            0 => self.rgba.0 as f64 / 255.0,
+            1 => self.rgba.1 as f64 / 255.0,
+            2 => self.rgba.2 as f64 / 255.0,
+            3 => self.hsv.0 / 360.0,
+            4 => self.hsv.1,
+            5 => self.hsv.2,
+            _ => self.rgba.3 as f64 / 255.0,
+        }
+    }
+
+    fn with_fraction(&self, slider: usize, fraction: f64) -> ColorSliders {
+        let fraction = fraction.clamp(0.0, 1.0);
+        let byte = (fraction * 255.0).round() as u8;
+        let (r, g, b, a) = self.rgba;
+        let (hue, sat, val) = self.hsv;
+        let mut sliders =
+            ColorSliders { rgba: self.rgba, hsv: self.hsv, dragging: None };
+        match slider {
+            0 => sliders.set_rgba((byte, g, b, a)),
+            1 => sliders.set_rgba((r, byte, b, a)),
+            2 => sliders.set_rgba((r, g, byte, a)),
+            3..=5 => {
+                sliders.hsv = match slider {
+                    3 => (fraction * 360.0, sat, val),
+                    4 => (hue, fraction, val),
+                    _ => (hue, sat, fraction),
+                };
+                let (r, g, b) = util::hsv_to_rgb(sliders.hsv);
+                sliders.rgba = (r, g, b, a);
+            }
+            _ => sliders.rgba.3 = byte,
+        }
+        sliders
+    }
+
+    fn value_string(&self, slider: usize) -> String {
+        match slider {
+            0 => format!("{}", self.rgba.0),
+            1 => format!("{}", self.rgba.1),
+            2 => format!("{}", self.rgba.2),
+            3 => format!("{}", self.hsv.0.round() as u32 % 360),
+            4 => format!("{}%", (self.hsv.1 * 100.0).round() as u32),
+            5 => format!("{}%", (self.hsv.2 * 100.0).round() as u32),
+            _ => format!("{}", self.rgba.3),
+        }
+    }
+
+    fn drag_to(&mut self, slider: usize, x: i32) -> Action<(u8, u8, u8, u8)> {
+        let fraction = ((x - ColorSliders::TRACK_LEFT) as f64)
+            / ((ColorSliders::TRACK_WIDTH - 1) as f64);
+        let sliders = self.with_fraction(slider, fraction);
+        self.rgba = sliders.rgba;
+        self.hsv = sliders.hsv;
+        Action::redraw().and_return(self.rgba)
+    }
+}
+
+impl GuiElement<(), (u8, u8, u8, u8)> for ColorSliders {
+    fn draw(&self, _: &(), resources: &Resources, canvas: &mut Canvas) {
+        let rect = canvas.rect();
+        canvas.fill_rect((128, 128, 128, 255), rect);
+        canvas.draw_rect((255, 255, 255, 255), rect);
+        let font = resources.font();
+        for (slider, &label) in SLIDER_LABELS.iter().enumerate() {
+            let top = ColorSliders::MARGIN
+                + ColorSliders::ROW_HEIGHT * (slider as i32);
+            canvas.draw_string(font, ColorSliders::MARGIN, top + 2, label);
+            for dx in 0..ColorSliders::TRACK_WIDTH {
+                let fraction =
+                    (dx as f64) / ((ColorSliders::TRACK_WIDTH - 1) as f64);
+                let (r, g, b, a) = self.with_fraction(slider, fraction).rgba;
+                let alpha = if slider == 6 { a } else { u8::MAX };
+                canvas.fill_rect(
+                    (r, g, b, alpha),
+                    Rect::new(
+                        ColorSliders::TRACK_LEFT + dx,
+                        top + 2,
+                        1,
+                        (ColorSliders::ROW_HEIGHT - 4) as u32,
+                    ),
+                );
+            }
+            let marker_x = ColorSliders::TRACK_LEFT
+                + (self.fraction(slider)
+                    * ((ColorSliders::TRACK_WIDTH - 1) as f64))
+                    .round() as i32;
+            let marker = Rect::new(
+                marker_x - 1,
+                top,
+                3,
+                ColorSliders::ROW_HEIGHT as u32,
+            );
+            canvas.fill_rect((0, 0, 0, 255), marker);
+            canvas.draw_rect((255, 255, 255, 255), marker);
+            canvas.draw_string(
+                font,
+                (ColorSliders::WIDTH as i32)
+                    - ColorSliders::MARGIN
+                    - ColorSliders::VALUE_WIDTH
+                    + 4,
+                top + 2,
+                &self.value_string(slider),
+            );
+        }
+    }
+
+    fn on_event(
+        &mut self,
+        event: &Event,
+        _: &mut (),
+    ) -> Action<(u8, u8, u8, u8)> {
+        match *event {
+            Event::MouseDown(pt, _) => {
+                let row = (pt.y() - ColorSliders::MARGIN)
+                    .div_euclid(ColorSliders::ROW_HEIGHT);
+                if row >= 0
+                    && (row as usize) < SLIDER_LABELS.len()
+                    && pt.x() >= ColorSliders::TRACK_LEFT - 2
+                    && pt.x()
+                        < ColorSliders::TRACK_LEFT
+                            + ColorSliders::TRACK_WIDTH
+                            + 2
+                {
+                    self.dragging = Some(row as usize);
+                    self.drag_to(row as usize, pt.x()).and_stop()
+                } else {
+                    Action::ignore().and_stop()
+                }
+            }
+            Event::MouseDrag(pt, _) => match self.dragging {
+                Some(slider) => self.drag_to(slider, pt.x()).and_stop(),
+                None => Action::ignore(),
+            },
+            Event::MouseUp => {
+                self.dragging = None;
+                Action::ignore()
+            }
+            _ => Action::ignore(),
+        }
+    }
+}
+
+//===========================================================================//
+
 struct FileMatch {
     file_name: String,
     file_path: String,
@@ -425,7 +636,7 @@ impl GuiElement<(), String> for FileMatch {
 
     fn on_event(&mut self, event: &Event, _: &mut ()) -> Action<String> {
         match event {
-            &Event::MouseDown(_) => {
+            &Event::MouseDown(_, _) => {
                 Action::redraw().and_return(self.file_path.clone())
             }
             _ => Action::ignore(),
@@ -516,6 +727,7 @@ pub struct ModalTextBox {
     mode: Mode,
     textbox: SubrectElement<TextBox>,
     rgba_panel: SubrectElement<RgbaPanel>,
+    color_sliders: SubrectElement<ColorSliders>,
     matches_panel: MatchesPanel,
 }
 
@@ -543,6 +755,15 @@ impl ModalTextBox {
                     RgbaPanel::HEIGHT,
                 ),
             ),
+            color_sliders: SubrectElement::new(
+                ColorSliders::new(),
+                Rect::new(
+                    left + LABEL_WIDTH,
+                    top + 22 + (RgbaPanel::HEIGHT as i32),
+                    ColorSliders::WIDTH,
+                    ColorSliders::HEIGHT,
+                ),
+            ),
             matches_panel: MatchesPanel::new(left + LABEL_WIDTH, top + 20),
         }
     }
@@ -552,6 +773,9 @@ impl ModalTextBox {
     }
 
     pub fn set_mode(&mut self, mode: Mode, text: String) {
+        if let Some(rgba) = util::parse_rgba_hex(&text) {
+            self.color_sliders.inner_mut().set_rgba(rgba);
+        }
         self.mode = mode;
         self.textbox.inner_mut().set_text(text);
         self.matches_panel.clear_matches();
@@ -584,6 +808,18 @@ impl ModalTextBox {
             Action::ignore()
         }
     }
+
+    fn update_color_preview(&mut self, state: &mut EditorState) {
+        if let Mode::SetColor(color) = self.mode {
+            let text = self.textbox.inner().text();
+            if let Some(rgba) = util::parse_rgba_hex(text) {
+                if self.color_sliders.inner().rgba != rgba {
+                    self.color_sliders.inner_mut().set_rgba(rgba);
+                }
+                state.set_color_preview(Some((color, rgba)));
+            }
+        }
+    }
 }
 
 impl GuiElement<EditorState, (Mode, String)> for ModalTextBox {
@@ -605,25 +841,41 @@ impl GuiElement<EditorState, (Mode, String)> for ModalTextBox {
             self.textbox.draw(&(), resources, canvas);
             if let Mode::SetColor(_) = self.mode {
                 self.rgba_panel.draw(&(), resources, canvas);
+                self.color_sliders.draw(&(), resources, canvas);
             } else if self.mode.tab_completion().is_some() {
                 self.matches_panel.draw(&(), resources, canvas);
             }
         }
         let label = match self.mode {
+            Mode::DropShadow => "Offset:",
             Mode::Edit => "Path:",
             Mode::Export => "Export:",
+            Mode::GenerateRamp => "Ramp:",
             Mode::Goto => "Goto:",
             Mode::Import => "Import:",
+            Mode::Italicize => "Slant:",
             Mode::LoadFile => "Load:",
+            Mode::MirrorAxes => "Axes:",
             Mode::NewGlyph => "Char:",
-            Mode::Resize => "Size:",
+            Mode::Offset | Mode::OffsetAll => "Offset:",
+            Mode::OnionSkin => "Onion:",
+            Mode::Reference => "Reference:",
+            Mode::ReferenceSettings => "Ref:",
+            Mode::Resize | Mode::ResizeCurrent => "Size:",
+            Mode::Rotate => "Angle:",
             Mode::SaveAs => "Save:",
+            Mode::ScaleTo => "Scale:",
             Mode::SetColor(_) => "Color:",
+            Mode::SetDither => "Dither:",
             Mode::SetGrid => "Grid:",
             Mode::SetMetadata => "Meta:",
             Mode::SetMetrics => "Metrics:",
+            Mode::SetRamps => "Ramps:",
             Mode::SetTag => "Tag:",
+            Mode::ShearHorz => "Shear X:",
+            Mode::ShearVert => "Shear Y:",
             Mode::TestSentence => "Text:",
+            Mode::Trim | Mode::TrimAll => "Trim:",
         };
         let font = resources.font();
         let text_width = font.text_width(label);
@@ -638,13 +890,14 @@ impl GuiElement<EditorState, (Mode, String)> for ModalTextBox {
     fn on_event(
         &mut self,
         event: &Event,
-        _: &mut EditorState,
+        state: &mut EditorState,
     ) -> Action<(Mode, String)> {
         if self.mode == Mode::Edit {
             return Action::ignore();
         }
         let mut action = match event {
             &Event::KeyDown(Keycode::Escape, _) => {
+                state.set_color_preview(None);
                 self.clear_mode();
                 Action::redraw().and_stop()
             }
@@ -684,6 +937,20 @@ impl GuiElement<EditorState, (Mode, String)> for ModalTextBox {
                 }
             }
         }
+        if !action.should_stop() {
+            if let Mode::SetColor(_) = self.mode {
+                let mut subaction =
+                    self.color_sliders.on_event(event, &mut ());
+                if let Some((r, g, b, a)) = subaction.take_value() {
+                    let text = format!("{:02X}{:02X}{:02X}{:02X}", r, g, b, a);
+                    self.textbox.inner_mut().set_text(text);
+                }
+                action.merge(subaction.but_no_value());
+            }
+        }
+        if action.should_redraw() {
+            self.update_color_preview(state);
+        }
         if !action.should_stop() {
             action = action.and_stop();
         }
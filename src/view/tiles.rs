@@ -66,12 +66,12 @@ impl GuiElement<EditorState, ()> for TileView {
                 left += glyph.right_edge();
             }
         } else {
-            let image = state.image();
+            let image = state.composite_image();
             let mut top = 0;
             while top < height as i32 {
                 let mut left = 0;
                 while left < width as i32 {
-                    canvas.draw_image(image, state.palette(), left, top, 1);
+                    canvas.draw_image(&image, state.palette(), left, top, 1);
                     left += image.width() as i32;
                 }
                 top += image.height() as i32;
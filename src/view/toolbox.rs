@@ -19,7 +19,7 @@
 
 use crate::canvas::{Canvas, Resources, ToolIcon};
 use crate::element::{Action, AggregateElement, GuiElement, SubrectElement};
-use crate::event::{Event, Keycode, NONE};
+use crate::event::{Event, KeyMod, Keycode, NONE, SHIFT};
 use crate::state::{EditorState, Tool};
 use sdl2::rect::{Point, Rect};
 
@@ -31,22 +31,29 @@ pub struct Toolbox {
 
 impl Toolbox {
     const WIDTH: u32 = 72;
-    const HEIGHT: u32 = 96;
+    const HEIGHT: u32 = 168;
 
     pub fn new(left: i32, top: i32) -> Toolbox {
         let elements: Vec<Box<dyn GuiElement<Tool, ()>>> = vec![
-            Toolbox::picker(2, 2, Tool::Pencil, Keycode::P),
-            Toolbox::picker(26, 2, Tool::PaintBucket, Keycode::K),
-            Toolbox::picker(50, 2, Tool::PaletteReplace, Keycode::V),
-            Toolbox::picker(2, 26, Tool::Watercolor, Keycode::W),
-            Toolbox::picker(26, 26, Tool::Checkerboard, Keycode::H),
-            Toolbox::picker(50, 26, Tool::PaletteSwap, Keycode::X),
-            Toolbox::picker(2, 50, Tool::Line, Keycode::I),
-            Toolbox::picker(26, 50, Tool::Rectangle, Keycode::R),
-            Toolbox::picker(50, 50, Tool::Oval, Keycode::O),
-            Toolbox::picker(2, 74, Tool::Eyedropper, Keycode::Y),
-            Toolbox::picker(26, 74, Tool::Select, Keycode::S),
-            Toolbox::picker(50, 74, Tool::Lasso, Keycode::L),
+            Toolbox::picker(2, 2, Tool::Pencil, Keycode::P, NONE),
+            Toolbox::picker(26, 2, Tool::PaintBucket, Keycode::K, NONE),
+            Toolbox::picker(50, 2, Tool::PaletteReplace, Keycode::V, NONE),
+            Toolbox::picker(2, 26, Tool::DitherBrush, Keycode::W, NONE),
+            Toolbox::picker(26, 26, Tool::DitherFill, Keycode::H, NONE),
+            Toolbox::picker(50, 26, Tool::PaletteSwap, Keycode::X, NONE),
+            Toolbox::picker(2, 50, Tool::Line, Keycode::I, NONE),
+            Toolbox::picker(26, 50, Tool::Rectangle, Keycode::R, NONE),
+            Toolbox::picker(50, 50, Tool::Oval, Keycode::O, NONE),
+            Toolbox::picker(2, 74, Tool::Eyedropper, Keycode::Y, NONE),
+            Toolbox::picker(26, 74, Tool::Select, Keycode::S, NONE),
+            Toolbox::picker(50, 74, Tool::Lasso, Keycode::L, NONE),
+            Toolbox::picker(2, 98, Tool::Shade, Keycode::J, NONE),
+            Toolbox::picker(26, 98, Tool::FilledRectangle, Keycode::R, SHIFT),
+            Toolbox::picker(50, 98, Tool::FilledOval, Keycode::O, SHIFT),
+            Toolbox::picker(2, 122, Tool::Polygon, Keycode::G, NONE),
+            Toolbox::picker(26, 122, Tool::Curve, Keycode::U, NONE),
+            Toolbox::picker(50, 122, Tool::Gradient, Keycode::N, NONE),
+            Toolbox::picker(2, 146, Tool::Measure, Keycode::M, NONE),
         ];
         Toolbox {
             element: SubrectElement::new(
@@ -61,9 +68,10 @@ impl Toolbox {
         y: i32,
         tool: Tool,
         key: Keycode,
+        kmod: KeyMod,
     ) -> Box<dyn GuiElement<Tool, ()>> {
         Box::new(SubrectElement::new(
-            ToolPicker::new(tool, key),
+            ToolPicker::new(tool, key, kmod),
             Rect::new(x, y, 20, 20),
         ))
     }
@@ -78,6 +86,22 @@ impl GuiElement<EditorState, ()> for Toolbox {
     ) {
         canvas.fill_rect((95, 95, 95, 255), self.element.rect());
         self.element.draw(&state.tool(), resources, canvas);
+        if state.brush().is_some() {
+            // Mark the pencil to show that it will stamp a custom brush.
+            let rect = self.element.rect();
+            canvas.fill_rect(
+                (255, 255, 0, 255),
+                Rect::new(rect.x() + 16, rect.y() + 16, 5, 5),
+            );
+        }
+        if state.pixel_perfect() {
+            // Mark the pencil to show that it will remove L-shaped corners.
+            let rect = self.element.rect();
+            canvas.fill_rect(
+                (0, 255, 255, 255),
+                Rect::new(rect.x() + 1, rect.y() + 16, 5, 5),
+            );
+        }
     }
 
     fn on_event(
@@ -99,26 +123,34 @@ impl GuiElement<EditorState, ()> for Toolbox {
 struct ToolPicker {
     tool: Tool,
     key: Keycode,
+    kmod: KeyMod,
     icon: ToolIcon,
 }
 
 impl ToolPicker {
-    fn new(tool: Tool, key: Keycode) -> ToolPicker {
+    fn new(tool: Tool, key: Keycode, kmod: KeyMod) -> ToolPicker {
         let icon = match tool {
-            Tool::Checkerboard => ToolIcon::Checkerboard,
+            Tool::Curve => ToolIcon::Curve,
+            Tool::DitherBrush => ToolIcon::DitherBrush,
+            Tool::DitherFill => ToolIcon::DitherFill,
             Tool::Eyedropper => ToolIcon::Eyedropper,
+            Tool::FilledOval => ToolIcon::FilledOval,
+            Tool::FilledRectangle => ToolIcon::FilledRectangle,
+            Tool::Gradient => ToolIcon::Gradient,
             Tool::Lasso => ToolIcon::Lasso,
             Tool::Line => ToolIcon::Line,
+            Tool::Measure => ToolIcon::Measure,
             Tool::Oval => ToolIcon::Oval,
             Tool::PaintBucket => ToolIcon::PaintBucket,
             Tool::PaletteReplace => ToolIcon::PaletteReplace,
             Tool::PaletteSwap => ToolIcon::PaletteSwap,
             Tool::Pencil => ToolIcon::Pencil,
+            Tool::Polygon => ToolIcon::Polygon,
             Tool::Rectangle => ToolIcon::Rectangle,
             Tool::Select => ToolIcon::Select,
-            Tool::Watercolor => ToolIcon::Watercolor,
+            Tool::Shade => ToolIcon::Shade,
         };
-        ToolPicker { tool, key, icon }
+        ToolPicker { tool, key, kmod, icon }
     }
 }
 
@@ -134,12 +166,12 @@ impl GuiElement<Tool, ()> for ToolPicker {
 
     fn on_event(&mut self, event: &Event, tool: &mut Tool) -> Action<()> {
         match event {
-            &Event::MouseDown(_) => {
+            &Event::MouseDown(_, _) => {
                 *tool = self.tool;
                 return Action::redraw().and_stop();
             }
             &Event::KeyDown(key, kmod) => {
-                if key == self.key && kmod == NONE {
+                if key == self.key && kmod == self.kmod {
                     *tool = self.tool;
                     return Action::redraw().and_stop();
                 }